@@ -0,0 +1,4 @@
+ALTER TABLE files ADD COLUMN file_size INTEGER;
+ALTER TABLE files ADD COLUMN modified_ms INTEGER;
+ALTER TABLE files ADD COLUMN inode INTEGER;
+ALTER TABLE files ADD COLUMN hash_algorithm TEXT NOT NULL DEFAULT 'sha256';
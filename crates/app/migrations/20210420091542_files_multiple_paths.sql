@@ -0,0 +1,14 @@
+CREATE TABLE files_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    file_path TEXT UNIQUE NOT NULL,
+    hash TEXT NOT NULL,
+    file_size INTEGER,
+    modified_ms INTEGER,
+    inode INTEGER,
+    hash_algorithm TEXT NOT NULL DEFAULT 'sha256'
+);
+INSERT INTO files_new(id, file_path, hash, file_size, modified_ms, inode, hash_algorithm)
+    SELECT id, file_path, hash, file_size, modified_ms, inode, hash_algorithm FROM files;
+DROP TABLE files;
+ALTER TABLE files_new RENAME TO files;
+CREATE INDEX files_hash ON files (hash);
@@ -0,0 +1,9 @@
+CREATE VIRTUAL TABLE pages_text USING fts5(
+    pdf_hash UNINDEXED,
+    page UNINDEXED,
+    content,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+CREATE TABLE pdfs_text_indexed (
+    pdf_hash TEXT PRIMARY KEY NOT NULL
+);
@@ -0,0 +1,3 @@
+-- The size of the longer side of the extracted page images in pixels.
+-- NULL if the pages were rendered with the default resolution of poppler.
+ALTER TABLE pdf_extracted_pages_dirs ADD COLUMN render_size INTEGER;
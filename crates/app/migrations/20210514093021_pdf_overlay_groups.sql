@@ -0,0 +1,8 @@
+-- Pages that belong to an overlay group, i.e. pages that only reveal more content of the previous page.
+-- Pages are 0-based, group_page is the first page of the group.
+CREATE TABLE pdf_overlay_groups (
+    pdf_hash TEXT NOT NULL,
+    page INTEGER NOT NULL,
+    group_page INTEGER NOT NULL,
+    PRIMARY KEY (pdf_hash, page)
+);
@@ -0,0 +1,3 @@
+-- The matching parameters the video was matched with, as JSON.
+-- NULL for videos that were matched before the parameters were recorded.
+ALTER TABLE videos ADD COLUMN matching_params TEXT;
@@ -1,11 +1,26 @@
-use crate::video_exts::is_video_ext;
-use anyhow::{anyhow, Result};
-use std::path::PathBuf;
+use crate::{
+    decks::is_image_deck,
+    file_sniffing::{sniff_file_kind, SniffedKind},
+    video_exts::{is_audio_ext, is_presentation_ext, is_video_ext},
+};
+use anyhow::{anyhow, Context, Result};
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum Kind {
     Pdf,
     Video,
+    /// A directory of slide images.
+    ImageDir,
+    /// A presentation (PPTX, ODP, ...) that is converted to pdf.
+    Presentation,
+}
+
+impl Kind {
+    /// Checks if this kind provides slides.
+    pub fn is_deck(&self) -> bool {
+        *self != Kind::Video
+    }
 }
 
 #[derive(Debug)]
@@ -16,40 +31,68 @@ pub struct CheckedPath {
 }
 
 impl CheckedPath {
+    /// Detects the kind of the file by its content.
+    /// Falls back to the (case-insensitive) file extension if the content is not recognized.
+    /// Directories are only accepted if they are image decks.
     pub fn from(path: PathBuf) -> Result<CheckedPath> {
         if path.is_dir() {
+            if is_image_deck(&path) {
+                return Ok(CheckedPath {
+                    path,
+                    kind: Kind::ImageDir,
+                    hash: None,
+                });
+            }
             return Err(anyhow!(
-                "The path '{}' is a directory, but a file was expected!",
+                "The path '{}' is a directory, but a file or a directory of slide images was expected!",
                 path.to_string_lossy()
             ));
         }
 
-        if let Some(ext) = path.extension() {
-            let ext_str = ext.to_string_lossy();
-            if ext_str == "pdf" {
-                Ok(CheckedPath {
-                    path,
-                    kind: Kind::Pdf,
-                    hash: None,
-                })
-            } else if is_video_ext(&ext_str) {
-                Ok(CheckedPath {
-                    path,
-                    kind: Kind::Video,
-                    hash: None,
-                })
-            } else {
-                Err(anyhow!(
+        let sniffed_kind = sniff_file_kind(&path)
+            .with_context(|| format!("Could not read file '{}'", path.to_string_lossy()))?;
+        let ext = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase());
+
+        let kind = match (sniffed_kind, ext) {
+            (Some(SniffedKind::Pdf), _) => Kind::Pdf,
+            (Some(SniffedKind::Video), _) => Kind::Video,
+            (Some(SniffedKind::Audio), _) => return Err(audio_only_error(&path)),
+            (Some(SniffedKind::VideoOrAudio), Some(ext)) if is_audio_ext(&ext) => {
+                return Err(audio_only_error(&path))
+            }
+            (Some(SniffedKind::VideoOrAudio), _) => Kind::Video,
+            (None, Some(ext)) if ext == "pdf" => Kind::Pdf,
+            (None, Some(ext)) if is_video_ext(&ext) => Kind::Video,
+            (None, Some(ext)) if is_presentation_ext(&ext) => Kind::Presentation,
+            (None, Some(ext)) if is_audio_ext(&ext) => return Err(audio_only_error(&path)),
+            (None, Some(ext)) => {
+                return Err(anyhow!(
                     "Unsupported file extension '{}' in path '{}'!",
-                    ext_str,
+                    ext,
                     path.to_string_lossy()
                 ))
             }
-        } else {
-            Err(anyhow!(
-                "Unsupported file extension in path '{}'!",
-                path.to_string_lossy()
-            ))
-        }
+            (None, None) => {
+                return Err(anyhow!(
+                    "Unsupported file extension in path '{}'!",
+                    path.to_string_lossy()
+                ))
+            }
+        };
+
+        Ok(CheckedPath {
+            path,
+            kind,
+            hash: None,
+        })
     }
 }
+
+fn audio_only_error(path: &Path) -> anyhow::Error {
+    anyhow!(
+        "The file '{}' only contains audio, but slides can only be matched with video frames!",
+        path.to_string_lossy()
+    )
+}
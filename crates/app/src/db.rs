@@ -6,13 +6,16 @@ use sqlx::{
     sqlite::{SqliteConnectOptions, SqlitePoolOptions},
     Connection, Error, Pool, Sqlite, SqliteConnection, Transaction,
 };
-use std::fs::create_dir_all;
+use std::{collections::HashMap, fs::create_dir_all};
 use std::{
     marker::PhantomData,
     path::{Path, PathBuf},
 };
 
-use crate::pdf_to_images::PdfPage;
+use crate::{
+    deck_to_images::DeckPage,
+    utils::{FileStat, HashAlgorithm},
+};
 use matching::Matching;
 
 #[derive(Clone)]
@@ -91,56 +94,165 @@ impl<'a> Db<'a, TransactionMarker> {
 
         let dir = data.dir.to_string_lossy();
         let dir = &dir as &str;
+        let render_size = data.render_size.map(|s| s as i64);
         sqlx::query!(
-            "INSERT INTO pdf_extracted_pages_dirs(pdf_hash, dir, finished) VALUES (?, ?, ?)",
+            "INSERT INTO pdf_extracted_pages_dirs(pdf_hash, dir, finished, render_size) VALUES (?, ?, ?, ?)",
             data.pdf_hash,
             dir,
             data.finished,
+            render_size,
+        )
+        .execute(self.get_conn_mut())
+        .await?;
+
+        Ok(())
+    }
+
+    /// Replaces the indexed text of all pages of the given pdf.
+    /// The first entry of `page_texts` is the text of the first page.
+    pub async fn set_pdf_page_texts(
+        &mut self,
+        pdf_hash: &str,
+        page_texts: &[String],
+    ) -> Result<(), Error> {
+        sqlx::query!("DELETE FROM pages_text WHERE pdf_hash = ?", pdf_hash)
+            .execute(self.get_conn_mut())
+            .await?;
+
+        for (page_idx, text) in page_texts.iter().enumerate() {
+            let page_idx = page_idx as i64;
+            sqlx::query!(
+                "INSERT INTO pages_text(pdf_hash, page, content) VALUES (?, ?, ?)",
+                pdf_hash,
+                page_idx,
+                text
+            )
+            .execute(self.get_conn_mut())
+            .await?;
+        }
+
+        sqlx::query!(
+            "INSERT OR IGNORE INTO pdfs_text_indexed(pdf_hash) VALUES (?)",
+            pdf_hash
+        )
+        .execute(self.get_conn_mut())
+        .await?;
+
+        Ok(())
+    }
+
+    /// Replaces the overlay groups of the given pdf.
+    /// Every group lists its 0-based pages in order.
+    pub async fn set_pdf_overlay_groups(
+        &mut self,
+        pdf_hash: &str,
+        groups: &[Vec<u32>],
+    ) -> Result<(), Error> {
+        sqlx::query!(
+            "DELETE FROM pdf_overlay_groups WHERE pdf_hash = ?",
+            pdf_hash
         )
         .execute(self.get_conn_mut())
         .await?;
 
+        for group in groups {
+            let group_page = group[0];
+            for &page in group {
+                sqlx::query!(
+                    "INSERT INTO pdf_overlay_groups(pdf_hash, page, group_page) VALUES (?, ?, ?)",
+                    pdf_hash,
+                    page,
+                    group_page
+                )
+                .execute(self.get_conn_mut())
+                .await?;
+            }
+        }
+
         Ok(())
     }
 
     pub async fn update_hashes<'c>(
         &mut self,
-        file_hashes: impl Iterator<Item = (&Path, &str)>,
+        file_hashes: impl Iterator<Item = (&'c Path, &'c str, &'c FileStat)>,
+        algorithm: HashAlgorithm,
     ) -> Result<(), Error> {
-        for (path, hash) in file_hashes {
+        let algorithm = algorithm.name();
+        for (path, hash, stat) in file_hashes {
             let path = path.to_string_lossy();
             let path = path.as_ref();
+            sqlx::query!("DELETE FROM files WHERE file_path = ?", path)
+                .execute(self.get_conn_mut())
+                .await?;
+
             sqlx::query!(
-                "DELETE FROM files WHERE file_path = ? OR hash = ?",
+                "
+                    INSERT INTO files(file_path, hash, file_size, modified_ms, inode, hash_algorithm)
+                    VALUES (?, ?, ?, ?, ?, ?)
+                ",
                 path,
-                hash
+                hash,
+                stat.size,
+                stat.modified_ms,
+                stat.inode,
+                algorithm,
             )
             .execute(self.get_conn_mut())
             .await?;
+        }
+        Ok(())
+    }
 
+    /// Moves all known files below `from` to the same relative path below `to`.
+    /// Returns the old and new paths of all moved files.
+    pub async fn relocate_files(
+        &mut self,
+        from: &Path,
+        to: &Path,
+    ) -> Result<Vec<(PathBuf, PathBuf)>, Error> {
+        let files = sqlx::query!("SELECT id, file_path FROM files")
+            .fetch_all(self.get_conn_mut())
+            .await?;
+
+        let mut relocated = Vec::new();
+        for file in files {
+            let old_path = PathBuf::from(file.file_path);
+            let relative_path = match old_path.strip_prefix(from) {
+                Ok(relative_path) => relative_path,
+                Err(_) => continue,
+            };
+            let new_path = to.join(relative_path);
+
+            let new_path_str = new_path.to_string_lossy();
+            let new_path_str = new_path_str.as_ref();
+            // Replaces the row of the new path, if it is already known.
             sqlx::query!(
-                "INSERT INTO files(file_path, hash) VALUES (?, ?)",
-                path,
-                hash
+                "UPDATE OR REPLACE files SET file_path = ? WHERE id = ?",
+                new_path_str,
+                file.id
             )
             .execute(self.get_conn_mut())
             .await?;
+
+            relocated.push((old_path, new_path));
         }
-        Ok(())
+        Ok(relocated)
     }
 
     pub async fn create_or_reset_video(
         &mut self,
         video_hash: &str,
         pdf_hashs: impl Iterator<Item = &str>,
+        matching_params: &str,
     ) -> Result<(), Error> {
         sqlx::query!("DELETE FROM videos WHERE video_hash = ?", video_hash)
             .execute(self.get_conn_mut())
             .await?;
 
         let row_id = sqlx::query!(
-            "INSERT INTO videos(video_hash, finished) VALUES (?, false)",
-            video_hash
+            "INSERT INTO videos(video_hash, finished, matching_params) VALUES (?, false, ?)",
+            video_hash,
+            matching_params
         )
         .execute(self.get_conn_mut())
         .await?
@@ -162,7 +274,7 @@ impl<'a> Db<'a, TransactionMarker> {
     pub async fn update_video_matchings<'c>(
         &mut self,
         video_hash: &str,
-        matchings: impl Iterator<Item = &'c Matching<&'c PdfPage<'c>>>,
+        matchings: impl Iterator<Item = &'c Matching<&'c DeckPage<'c>>>,
     ) -> Result<(), Error> {
         let video_id = sqlx::query!("SELECT id FROM videos WHERE video_hash = ?", video_hash)
             .fetch_one(self.get_conn_mut())
@@ -174,7 +286,7 @@ impl<'a> Db<'a, TransactionMarker> {
             .await?;
 
         for matching in matchings {
-            let pdf_hash = matching.image.map(|p| p.pdf_hash);
+            let pdf_hash = matching.image.map(|p| p.deck_hash);
             let video_ms = matching.video_time.as_millis() as u32;
             let page_offset = matching.image.map(|p| (p.page_nr - 1) as u32).unwrap_or(0);
             sqlx::query!(
@@ -192,6 +304,16 @@ impl<'a> Db<'a, TransactionMarker> {
 }
 
 #[derive(Deserialize, Serialize)]
+pub struct PageSearchHit {
+    pdf_hash: String,
+    page_idx: u32,
+    /// The matched text, with matched terms enclosed in brackets.
+    snippet: String,
+    /// All video segments that show the page.
+    segments: Vec<PdfVideoMatching>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
 pub struct PdfVideoMatching {
     video_offset_ms: u32,
     pdf_hash: String,
@@ -200,6 +322,32 @@ pub struct PdfVideoMatching {
     duration_ms: u32,
 }
 
+/// Shows the pages of an overlay group as their first page
+/// and merges consecutive segments that show the same page.
+/// `overlay_groups` maps pages to the first page of their group.
+pub fn collapse_overlays(
+    matchings: Vec<PdfVideoMatching>,
+    overlay_groups: &HashMap<u32, u32>,
+) -> Vec<PdfVideoMatching> {
+    let mut result: Vec<PdfVideoMatching> = Vec::new();
+    for mut matching in matchings {
+        if let Some(group_page) = overlay_groups.get(&matching.page_idx) {
+            matching.page_idx = *group_page;
+        }
+        match result.last_mut() {
+            Some(last)
+                if last.video_hash == matching.video_hash
+                    && last.page_idx == matching.page_idx
+                    && last.video_offset_ms + last.duration_ms == matching.video_offset_ms =>
+            {
+                last.duration_ms += matching.duration_ms;
+            }
+            _ => result.push(matching),
+        }
+    }
+    result
+}
+
 impl<'a, T> Db<'a, T> {
     pub async fn begin_trans<'c>(&'c mut self) -> Result<Db<'c, TransactionMarker>, Error> {
         let trans = self.get_conn_mut().begin().await?;
@@ -270,18 +418,113 @@ impl<'a, T> Db<'a, T> {
         Ok(result)
     }
 
+    /// Returns whether the page texts of the pdf have already been stored in the search index.
+    pub async fn is_pdf_text_indexed(&mut self, pdf_hash: &str) -> Result<bool, Error> {
+        let result = sqlx::query!(
+            "SELECT pdf_hash FROM pdfs_text_indexed WHERE pdf_hash = ?",
+            pdf_hash
+        )
+        .fetch_optional(self.get_conn_mut())
+        .await?;
+        Ok(result.is_some())
+    }
+
+    /// Finds the pages whose text contains all words of the query,
+    /// together with the video segments that show these pages.
+    pub async fn search_pages(&mut self, query: &str, limit: u32) -> Result<Vec<PageSearchHit>> {
+        let fts_query = to_fts_query(query);
+        if fts_query.is_empty() {
+            return Ok(Vec::new());
+        }
+        let limit = limit as i64;
+
+        let hits = sqlx::query!(
+            r#"
+                SELECT
+                    pdf_hash as "pdf_hash!: String",
+                    page as "page!: i64",
+                    snippet(pages_text, 2, '[', ']', '...', 12) as "snippet!: String"
+                FROM pages_text
+                WHERE pages_text MATCH ?
+                ORDER BY rank
+                LIMIT ?
+            "#,
+            fts_query,
+            limit
+        )
+        .fetch_all(self.get_conn_mut())
+        .await?;
+
+        let mut matchings_by_pdf = HashMap::<String, Vec<PdfVideoMatching>>::new();
+        let mut result = Vec::new();
+        for hit in hits {
+            if !matchings_by_pdf.contains_key(&hit.pdf_hash) {
+                let matchings = self.get_pdf_video_matchings(&hit.pdf_hash).await?;
+                matchings_by_pdf.insert(hit.pdf_hash.clone(), matchings);
+            }
+            let page_idx = hit.page as u32;
+            let segments = matchings_by_pdf[&hit.pdf_hash]
+                .iter()
+                .filter(|m| m.page_idx == page_idx)
+                .cloned()
+                .collect();
+
+            result.push(PageSearchHit {
+                pdf_hash: hit.pdf_hash,
+                page_idx,
+                snippet: hit.snippet,
+                segments,
+            });
+        }
+
+        Ok(result)
+    }
+
+    /// Returns the most recently seen path of the file with the given hash that still exists.
     pub async fn get_path(&mut self, hash: &str) -> Result<Option<PathBuf>> {
         let result = sqlx::query!(
             "
                 SELECT file_path FROM files
                 WHERE hash = ?
+                ORDER BY id DESC
             ",
             hash
         )
+        .fetch_all(self.get_conn_mut())
+        .await?;
+
+        Ok(result
+            .into_iter()
+            .map(|r| PathBuf::from(r.file_path))
+            .find(|p| p.exists()))
+    }
+
+    /// Returns the hash that was computed for the file at `path`,
+    /// if the file did not change since then.
+    pub async fn find_cached_hash(
+        &mut self,
+        path: &Path,
+        stat: &FileStat,
+        algorithm: HashAlgorithm,
+    ) -> Result<Option<String>, Error> {
+        let path = path.to_string_lossy();
+        let path = path.as_ref();
+        let algorithm = algorithm.name();
+        let result = sqlx::query!(
+            "
+                SELECT hash FROM files
+                WHERE file_path = ? AND file_size = ? AND modified_ms = ? AND inode IS ? AND hash_algorithm = ?
+            ",
+            path,
+            stat.size,
+            stat.modified_ms,
+            stat.inode,
+            algorithm,
+        )
         .fetch_optional(self.get_conn_mut())
         .await?;
 
-        Ok(result.map(|r| r.file_path.into()))
+        Ok(result.map(|r| r.hash))
     }
 
     pub async fn find_mapping_info(
@@ -321,7 +564,7 @@ impl<'a, T> Db<'a, T> {
     ) -> Result<Option<PdfExtractedPagesDir>, Error> {
         let results = sqlx::query!(
             "
-                SELECT pdf_hash, dir, finished FROM pdf_extracted_pages_dirs
+                SELECT pdf_hash, dir, finished, render_size FROM pdf_extracted_pages_dirs
                 WHERE pdf_hash = ?
             ",
             pdf_hash
@@ -334,12 +577,28 @@ impl<'a, T> Db<'a, T> {
                 pdf_hash: record.pdf_hash.clone(),
                 dir: record.dir.clone().into(),
                 finished: record.finished,
+                render_size: record.render_size.map(|s| s as u32),
             }))
         } else {
             Ok(None)
         }
     }
 
+    /// Maps the 0-based pages of the pdf that belong to an overlay group to the first page of their group.
+    pub async fn get_pdf_overlay_groups(&mut self, pdf_hash: &str) -> Result<HashMap<u32, u32>> {
+        let results = sqlx::query!(
+            "SELECT page, group_page FROM pdf_overlay_groups WHERE pdf_hash = ?",
+            pdf_hash
+        )
+        .fetch_all(self.get_conn_mut())
+        .await?;
+
+        Ok(results
+            .into_iter()
+            .map(|r| (r.page as u32, r.group_page as u32))
+            .collect())
+    }
+
     fn get_conn_mut(&mut self) -> &mut SqliteConnection {
         match &mut self.db {
             DbImpl::Conn(conn) => conn,
@@ -348,8 +607,30 @@ impl<'a, T> Db<'a, T> {
     }
 }
 
+/// Converts user input into an FTS5 query that matches all words, the last one as prefix.
+fn to_fts_query(query: &str) -> String {
+    let words: Vec<&str> = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+    words
+        .iter()
+        .enumerate()
+        .map(|(idx, w)| {
+            if idx + 1 == words.len() {
+                format!("\"{}\"*", w)
+            } else {
+                format!("\"{}\"", w)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 pub struct PdfExtractedPagesDir {
     pub pdf_hash: String,
     pub dir: PathBuf,
     pub finished: bool,
+    /// The size of the longer side of the page images in pixels.
+    pub render_size: Option<u32>,
 }
@@ -0,0 +1,316 @@
+use crate::{
+    checked_path::Kind,
+    db::{DbPool, PdfExtractedPagesDir},
+    decks::{get_deck_pdf, list_deck_images},
+    utils::get_temp_path_key,
+    HashedFile,
+};
+use anyhow::{anyhow, Result};
+use async_std::task::block_on;
+use lazy_static::lazy_static;
+use matching::{MatchableImage, ProgressReporter};
+use pdftocairo::{default_rasterizer, PageSelection, PdfRasterizer, ProgressInfo};
+use rand::Rng;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::{
+    collections::HashMap,
+    fs::remove_dir_all,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+lazy_static! {
+    /// pdfium (if enabled) must only be loaded once.
+    static ref RASTERIZER: Box<dyn PdfRasterizer> = default_rasterizer();
+}
+
+/// A slide of a deck, i.e. a page of a pdf or presentation or an image of an image directory.
+#[derive(Debug, Eq, PartialEq)]
+pub struct DeckPage<'t> {
+    pub deck_path: &'t Path,
+    pub deck_hash: &'t str,
+    pub image_path: PathBuf,
+    /// 1-based.
+    pub page_nr: usize,
+}
+
+impl<'a> MatchableImage for &DeckPage<'a> {
+    fn get_path(&self) -> &Path {
+        &self.image_path
+    }
+
+    fn deck_position(&self) -> Option<(&str, usize)> {
+        Some((self.deck_hash, self.page_nr))
+    }
+}
+
+/// Where the selected page images of a deck come from.
+enum DeckSource {
+    /// The pages are rendered from this pdf.
+    Pdf {
+        pdf_path: PathBuf,
+        selection: Option<PageSelection>,
+        /// The number of selected pages.
+        page_count: u32,
+    },
+    /// The pages are the images themselves, with their page numbers.
+    Images(Vec<(usize, PathBuf)>),
+}
+
+impl DeckSource {
+    fn of(deck: &HashedFile, selection: Option<&PageSelection>) -> Result<DeckSource> {
+        let is_selected = |page_nr: u32| selection.is_none_or(|s| s.contains(page_nr));
+        if deck.kind == Kind::ImageDir {
+            let images = list_deck_images(&deck.path)?
+                .into_iter()
+                .enumerate()
+                .map(|(idx, image_path)| (idx + 1, image_path))
+                .filter(|(page_nr, _)| is_selected(*page_nr as u32))
+                .collect();
+            return Ok(DeckSource::Images(images));
+        }
+        let pdf_path = get_deck_pdf(&deck.path, &deck.hash, deck.kind)?;
+        let page_count = RASTERIZER.page_count(&pdf_path)?;
+        Ok(DeckSource::Pdf {
+            pdf_path,
+            selection: selection.cloned(),
+            page_count: (1..=page_count).filter(|p| is_selected(*p)).count() as u32,
+        })
+    }
+
+    fn page_count(&self) -> u32 {
+        match self {
+            DeckSource::Pdf { page_count, .. } => *page_count,
+            DeckSource::Images(images) => images.len() as u32,
+        }
+    }
+}
+
+/// Provides the pages of the given decks as images.
+/// Pdfs and presentations are rendered, the images of image decks are used as they are.
+/// Of the decks in `page_selections`, only the selected pages are provided.
+/// If `render_size` is set, the pages are scaled so that their longer side has this size in pixels,
+/// otherwise poppler's default resolution of 150 DPI is used.
+pub fn decks_to_images<'t>(
+    decks: &Vec<&'t HashedFile>,
+    page_selections: &HashMap<String, PageSelection>,
+    render_size: Option<u32>,
+    db_pool: &DbPool,
+    progress_reporter: ProgressReporter,
+) -> Result<Vec<DeckPage<'t>>> {
+    let mut decks = decks.clone();
+    decks.dedup_by_key(|p| &p.hash); // Remove duplicated decks
+
+    // Presentations are converted one after another, so this is not done in parallel.
+    let sources = decks
+        .iter()
+        .map(|d| DeckSource::of(d, page_selections.get(&d.hash)))
+        .collect::<Result<Vec<_>>>()?;
+    let total_page_count: u32 = sources.iter().map(|s| s.page_count()).sum();
+
+    // The pdfs are rendered in parallel and each of them with several processes.
+    // Their processes share the cores, so that at most one process per core is started.
+    let pdf_count = sources
+        .iter()
+        .filter(|s| matches!(s, DeckSource::Pdf { .. }))
+        .count();
+    let threads_per_pdf = (rayon::current_num_threads() / pdf_count.max(1)).max(1);
+
+    let progresses = Arc::new(Mutex::new(HashMap::new()));
+    let report_progress = |deck_hash: &'t str, processed_pages: u32| {
+        let mut map = progresses.lock().unwrap();
+        map.insert(deck_hash, processed_pages);
+        let total_processed_pages: u32 = map.values().sum();
+        progress_reporter.report(
+            total_processed_pages as u64,
+            total_page_count as u64,
+            "Extracting slides...",
+        );
+    };
+
+    let decks_with_sources: Vec<(&'t HashedFile, &DeckSource)> =
+        decks.iter().copied().zip(sources.iter()).collect();
+    let result: Result<Vec<Vec<DeckPage<'t>>>> = decks_with_sources
+        .par_iter()
+        .map(|&(f, source)| -> Result<Vec<DeckPage<'t>>> {
+            let (pdf_path, selection) = match source {
+                DeckSource::Images(images) => {
+                    report_progress(&f.hash, images.len() as u32);
+                    return Ok(images
+                        .iter()
+                        .map(|(page_nr, image_path)| DeckPage {
+                            deck_path: &f.path,
+                            deck_hash: &f.hash,
+                            image_path: image_path.clone(),
+                            page_nr: *page_nr,
+                        })
+                        .collect());
+                }
+                DeckSource::Pdf {
+                    pdf_path,
+                    selection,
+                    ..
+                } => (pdf_path, selection),
+            };
+
+            let mut db = block_on(db_pool.db())?;
+            let mut tx = block_on(db.begin_trans())?;
+            let result: Option<PdfExtractedPagesDir> =
+                block_on(tx.get_pdf_extracted_pages_dir(&f.hash))?;
+
+            let (target_dir, finished, replaced_dir) = match result {
+                Some(data) if data.finished && data.render_size == render_size => {
+                    (data.dir, true, None)
+                }
+                data => {
+                    let mut rng = rand::thread_rng();
+                    let rand_idx: u128 = rng.gen();
+                    (
+                        get_temp_path_key("slides", &format!("{}-{:?}", &f.hash, rand_idx)),
+                        false,
+                        data.map(|data| data.dir),
+                    )
+                }
+            };
+
+            if !finished {
+                block_on(tx.set_pdf_extracted_pages_dir(&PdfExtractedPagesDir {
+                    dir: target_dir.clone(),
+                    finished: false,
+                    pdf_hash: f.hash.clone(),
+                    render_size,
+                }))?;
+            }
+
+            block_on(tx.commit())?;
+
+            // The pages of the replaced row are not referenced anymore.
+            if let Some(replaced_dir) = replaced_dir {
+                if let Err(err) = remove_dir_all(&replaced_dir) {
+                    if err.kind() != ErrorKind::NotFound {
+                        println!(
+                            "Warning: Could not delete '{}': {}",
+                            replaced_dir.to_string_lossy(),
+                            err
+                        );
+                    }
+                }
+            }
+
+            let pages = pdf_to_images(
+                f,
+                pdf_path,
+                &target_dir,
+                selection.clone(),
+                render_size,
+                threads_per_pdf,
+                |processed_pages| report_progress(&f.hash, processed_pages),
+            )?;
+
+            if !finished {
+                let mut tx = block_on(db.begin_trans())?;
+                block_on(tx.set_pdf_extracted_pages_dir(&PdfExtractedPagesDir {
+                    dir: target_dir.clone(),
+                    finished: true,
+                    pdf_hash: f.hash.clone(),
+                    render_size,
+                }))?;
+                block_on(tx.commit())?;
+            }
+
+            Ok(pages)
+        })
+        .collect();
+
+    progress_reporter.report(
+        total_page_count as u64,
+        total_page_count as u64,
+        "Slide extraction successful.",
+    );
+
+    let flatten = result.into_iter().flatten().flatten().collect();
+    Ok(flatten)
+}
+
+fn pdf_to_images<'t>(
+    deck: &'t HashedFile,
+    pdf_path: &Path,
+    target_dir: &Path,
+    selection: Option<PageSelection>,
+    render_size: Option<u32>,
+    threads: usize,
+    progress: impl Fn(u32),
+) -> Result<Vec<DeckPage<'t>>> {
+    let report_progress = |p: ProgressInfo| progress(p.processed_pages);
+    let pages = RASTERIZER.render_pages(
+        pdf_path,
+        target_dir,
+        pdftocairo::Options {
+            progress: Some(&report_progress),
+            scale_to: render_size,
+            reuse_target_dir_content: true,
+            selection,
+            threads: Some(threads),
+            ..pdftocairo::Options::default()
+        },
+    )?;
+
+    Ok(pages
+        .into_iter()
+        .map(|p| DeckPage {
+            page_nr: p.page_nr as usize,
+            image_path: p.image_path,
+            deck_path: &deck.path,
+            deck_hash: &deck.hash,
+        })
+        .collect())
+}
+
+/// Returns an image of the given page (1-based) that is scaled so that its longer side has `size` pixels.
+/// Rendered images are cached. The images of image decks are returned as they are.
+pub fn render_page_preview(
+    deck_path: &Path,
+    deck_hash: &str,
+    deck_kind: Kind,
+    page_nr: u32,
+    size: u32,
+) -> Result<PathBuf> {
+    let page_not_found = || anyhow!("Page {} does not exist", page_nr);
+
+    if deck_kind == Kind::ImageDir {
+        return list_deck_images(deck_path)?
+            .into_iter()
+            .nth(
+                (page_nr as usize)
+                    .checked_sub(1)
+                    .ok_or_else(page_not_found)?,
+            )
+            .ok_or_else(page_not_found);
+    }
+
+    let pdf_path = get_deck_pdf(deck_path, deck_hash, deck_kind)?;
+    let target_dir = get_temp_path_key(
+        "page-previews",
+        &format!("{}-{}-{}", deck_hash, page_nr, size),
+    );
+
+    let pages = RASTERIZER.render_pages(
+        &pdf_path,
+        &target_dir,
+        pdftocairo::Options {
+            progress: None,
+            first_page: Some(page_nr),
+            last_page: Some(page_nr),
+            scale_to: Some(size),
+            reuse_target_dir_content: true,
+            ..pdftocairo::Options::default()
+        },
+    )?;
+
+    pages
+        .into_iter()
+        .next()
+        .map(|p| p.image_path)
+        .ok_or_else(page_not_found)
+}
@@ -0,0 +1,123 @@
+use crate::{
+    checked_path::Kind,
+    utils::get_temp_path_key,
+    video_exts::{is_image_ext, is_presentation_ext, is_video_ext},
+};
+use anyhow::{anyhow, Context, Result};
+use lazy_static::lazy_static;
+use std::{
+    fs::{create_dir_all, rename},
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+};
+
+fn lowercase_ext(path: &Path) -> Option<String> {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+}
+
+/// A directory is an image deck if it directly contains images,
+/// but no pdfs, presentations, videos or subdirectories.
+///
+/// Only directories that are passed explicitly are checked, so that folders of figures
+/// inside a scanned directory tree do not become decks.
+pub fn is_image_deck(dir: &Path) -> bool {
+    let entries = match dir.read_dir() {
+        Ok(entries) => entries,
+        Err(_) => return false,
+    };
+
+    let mut has_images = false;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            return false;
+        }
+        if !path.is_file() {
+            continue;
+        }
+        match lowercase_ext(&path) {
+            Some(ext) if is_image_ext(&ext) => has_images = true,
+            Some(ext) if ext == "pdf" || is_presentation_ext(&ext) || is_video_ext(&ext) => {
+                return false
+            }
+            _ => {}
+        }
+    }
+    has_images
+}
+
+/// Lists the images of an image deck in page order,
+/// which is the natural order of their file names (`slide2.png` comes before `slide10.png`).
+pub fn list_deck_images(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut images = Vec::<PathBuf>::new();
+    for entry in dir.read_dir()? {
+        let path = entry?.path();
+        if path.is_file() && lowercase_ext(&path).is_some_and(|ext| is_image_ext(&ext)) {
+            images.push(path);
+        }
+    }
+
+    images.sort_by(|a, b| {
+        natord::compare(
+            &a.file_name().unwrap().to_string_lossy(),
+            &b.file_name().unwrap().to_string_lossy(),
+        )
+    });
+    Ok(images)
+}
+
+lazy_static! {
+    /// LibreOffice cannot run several conversions with the same user profile at once.
+    static ref SOFFICE_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// Converts a presentation to pdf with LibreOffice. The pdf is cached.
+pub fn convert_presentation(path: &Path, hash: &str) -> Result<PathBuf> {
+    let _guard = SOFFICE_LOCK.lock().unwrap();
+
+    let target_dir = get_temp_path_key("presentations", hash);
+    let target = target_dir.join("deck.pdf");
+    if target.exists() {
+        return Ok(target);
+    }
+
+    // Converts to a separate directory first, so that aborted conversions are not used.
+    let out_dir = target_dir.join("out");
+    create_dir_all(&out_dir)?;
+    let output = Command::new("soffice")
+        .args(["--headless", "--convert-to", "pdf", "--outdir"])
+        .arg(&out_dir)
+        .arg(path)
+        .output()
+        .context("Could not run soffice. Is LibreOffice installed?")?;
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let converted = out_dir.join(format!("{}.pdf", stem));
+    // soffice also exits successfully if the file could not be converted.
+    if !output.status.success() || !converted.exists() {
+        return Err(anyhow!(
+            "LibreOffice failed to convert '{}': {}",
+            path.to_string_lossy(),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    rename(&converted, &target)?;
+
+    Ok(target)
+}
+
+/// Returns a pdf with the pages of the given deck.
+/// Presentations are converted, image decks are not supported.
+pub fn get_deck_pdf(path: &Path, hash: &str, kind: Kind) -> Result<PathBuf> {
+    match kind {
+        Kind::Pdf => Ok(path.to_owned()),
+        Kind::Presentation => convert_presentation(path, hash),
+        Kind::ImageDir | Kind::Video => Err(anyhow!(
+            "'{}' cannot be converted to a pdf",
+            path.to_string_lossy()
+        )),
+    }
+}
@@ -0,0 +1,151 @@
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum SniffedKind {
+    Pdf,
+    Video,
+    /// An audio-only file. OpenCV cannot extract frames from it.
+    Audio,
+    /// A container that holds videos as well as audio-only files, like Matroska or MP4.
+    /// Only the file extension tells them apart.
+    VideoOrAudio,
+}
+
+/// Detects the kind of a file by looking at its first bytes.
+/// Returns `None` if the signature is not known.
+pub fn sniff_file_kind(path: &Path) -> io::Result<Option<SniffedKind>> {
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    File::open(path)?
+        .take(HEADER_LEN as u64)
+        .read_to_end(&mut header)?;
+    Ok(sniff_header(&header))
+}
+
+/// PDF readers accept the `%PDF-` marker anywhere in the first 1024 bytes.
+const HEADER_LEN: usize = 1024;
+
+fn sniff_header(header: &[u8]) -> Option<SniffedKind> {
+    let starts_with = |offset: usize, signature: &[u8]| {
+        header.len() >= offset + signature.len()
+            && &header[offset..offset + signature.len()] == signature
+    };
+
+    if header.windows(5).any(|w| w == b"%PDF-") {
+        return Some(SniffedKind::Pdf);
+    }
+
+    // ISO base media (mp4, mov, 3gp, m4a)
+    if starts_with(4, b"ftyp") {
+        let is_audio = [b"M4A ", b"M4B ", b"M4P "]
+            .iter()
+            .any(|brand| starts_with(8, *brand));
+        return Some(if is_audio {
+            SniffedKind::Audio
+        } else {
+            SniffedKind::VideoOrAudio
+        });
+    }
+    if starts_with(4, b"moov") || starts_with(4, b"mdat") || starts_with(4, b"wide") {
+        return Some(SniffedKind::VideoOrAudio);
+    }
+
+    // Matroska and WebM (EBML header), also used by audio-only `.mka` and `.weba` files
+    if starts_with(0, &[0x1A, 0x45, 0xDF, 0xA3]) {
+        return Some(SniffedKind::VideoOrAudio);
+    }
+
+    if starts_with(0, b"RIFF") {
+        if starts_with(8, b"AVI ") {
+            return Some(SniffedKind::Video);
+        }
+        if starts_with(8, b"WAVE") {
+            return Some(SniffedKind::Audio);
+        }
+        return None;
+    }
+
+    if starts_with(0, b"FLV") {
+        return Some(SniffedKind::Video);
+    }
+
+    // ASF is not detected here: wmv and wma cannot be told apart without parsing the stream headers.
+
+    // MPEG program stream
+    if starts_with(0, &[0x00, 0x00, 0x01, 0xBA]) || starts_with(0, &[0x00, 0x00, 0x01, 0xB3]) {
+        return Some(SniffedKind::Video);
+    }
+    // MPEG transport stream
+    if header.len() > 188 && header[0] == 0x47 && header[188] == 0x47 {
+        return Some(SniffedKind::Video);
+    }
+
+    if starts_with(0, b"OggS") {
+        let has_video_stream = [&b"\x80theora"[..], &b"\x01video"[..], &b"OVP80"[..]]
+            .iter()
+            .any(|codec| header.windows(codec.len()).any(|w| w == *codec));
+        return Some(if has_video_stream {
+            SniffedKind::Video
+        } else {
+            SniffedKind::Audio
+        });
+    }
+
+    if starts_with(0, b"fLaC")
+        || starts_with(0, b"ID3")
+        || starts_with(0, b"#!AMR")
+        || starts_with(0, b"MAC ")
+        || starts_with(0, b"wvpk")
+        || (starts_with(0, b"FORM") && (starts_with(8, b"AIFF") || starts_with(8, b"AIFC")))
+    {
+        return Some(SniffedKind::Audio);
+    }
+    // MPEG audio frame sync (mp3, aac in adts)
+    if header.len() >= 2 && header[0] == 0xFF && header[1] & 0xE0 == 0xE0 {
+        return Some(SniffedKind::Audio);
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff_header() {
+        assert_eq!(
+            sniff_header(b"%PDF-1.5\n%\xE2\xE3\xCF\xD3"),
+            Some(SniffedKind::Pdf)
+        );
+        assert_eq!(
+            sniff_header(b"\x00\x00\x00\x20ftypisom\x00\x00\x02\x00"),
+            Some(SniffedKind::VideoOrAudio)
+        );
+        assert_eq!(
+            sniff_header(b"\x00\x00\x00\x20ftypM4A \x00\x00\x00\x00"),
+            Some(SniffedKind::Audio)
+        );
+        assert_eq!(
+            sniff_header(b"\x1A\x45\xDF\xA3\x9F\x42\x86\x81"),
+            Some(SniffedKind::VideoOrAudio)
+        );
+        assert_eq!(
+            sniff_header(b"RIFF\x00\x00\x00\x00AVI LIST"),
+            Some(SniffedKind::Video)
+        );
+        assert_eq!(
+            sniff_header(b"RIFF\x00\x00\x00\x00WAVEfmt "),
+            Some(SniffedKind::Audio)
+        );
+        assert_eq!(
+            sniff_header(b"fLaC\x00\x00\x00\x22"),
+            Some(SniffedKind::Audio)
+        );
+        assert_eq!(sniff_header(b"hello world"), None);
+        assert_eq!(sniff_header(b""), None);
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{checked_path::CheckedPath, decks::is_image_deck};
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::{
+    collections::HashSet,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+use walkdir::WalkDir;
+
+/// Decides which files found while scanning a directory are used.
+/// Files that are passed explicitly are never filtered.
+pub struct PathFilter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+impl PathFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<PathFilter> {
+        let include = if include.is_empty() {
+            None
+        } else {
+            Some(build_glob_set(include)?)
+        };
+
+        Ok(PathFilter {
+            include,
+            exclude: build_glob_set(exclude)?,
+        })
+    }
+
+    /// `relative_path` is relative to the directory that is being scanned.
+    fn is_match(&self, relative_path: &Path) -> bool {
+        if self.exclude.is_match(relative_path) {
+            return false;
+        }
+        match &self.include {
+            Some(include) => include.is_match(relative_path),
+            None => true,
+        }
+    }
+}
+
+fn build_glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(
+            Glob::new(pattern).with_context(|| format!("Invalid glob pattern '{}'", pattern))?,
+        );
+    }
+    Ok(builder.build()?)
+}
+
+/// Resolves the given files and directories to the list of decks and videos to process.
+/// Directories are scanned recursively, unsupported files in them are ignored.
+/// A directory that is passed explicitly and only contains images is used as a deck itself.
+/// Directories found while scanning are never used as decks.
+pub fn collect_input_files(paths: &[PathBuf], filter: &PathFilter) -> Result<Vec<CheckedPath>> {
+    let mut seen = HashSet::<PathBuf>::new();
+    let mut result = Vec::new();
+
+    for path in paths {
+        if path.is_dir() && !is_image_deck(path) {
+            let mut entries = Vec::new();
+            for entry in WalkDir::new(path).follow_links(true) {
+                let entry = entry.with_context(|| {
+                    format!("Could not scan directory '{}'", path.to_string_lossy())
+                })?;
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+                let relative_path = entry.path().strip_prefix(path).unwrap_or(entry.path());
+                if !filter.is_match(relative_path) {
+                    continue;
+                }
+                if let Ok(checked_path) = CheckedPath::from(entry.into_path()) {
+                    entries.push(checked_path);
+                }
+            }
+            entries.sort_by(|a, b| a.path.cmp(&b.path));
+            for entry in entries {
+                if seen.insert(entry.path.clone()) {
+                    result.push(entry);
+                }
+            }
+        } else {
+            let checked_path = CheckedPath::from(path.clone())?;
+            if seen.insert(checked_path.path.clone()) {
+                result.push(checked_path);
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+/// Reads a list of files and directories from a file, one path per line.
+/// Empty lines and lines starting with `#` are ignored.
+/// Relative paths are resolved against the directory of the file list.
+///
+/// Use a project manifest to match groups of pdfs and videos independently.
+pub fn read_file_list(list_path: &Path) -> Result<Vec<PathBuf>> {
+    let content = read_to_string(list_path)
+        .with_context(|| format!("Could not read file list '{}'", list_path.to_string_lossy()))?;
+    let base_dir = list_path.parent().unwrap_or_else(|| Path::new(""));
+
+    Ok(content
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| base_dir.join(line))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, write};
+    use tempdir::TempDir;
+
+    fn filter(include: &[&str], exclude: &[&str]) -> PathFilter {
+        let to_strings =
+            |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        PathFilter::new(&to_strings(include), &to_strings(exclude)).unwrap()
+    }
+
+    #[test]
+    fn test_path_filter() {
+        let all = filter(&[], &[]);
+        assert!(all.is_match(Path::new("lecture1.pdf")));
+
+        let filter = filter(&["*.pdf", "recordings/*.mp4"], &["**/drafts/**"]);
+        assert!(filter.is_match(Path::new("lecture1.pdf")));
+        assert!(filter.is_match(Path::new("recordings/week1.mp4")));
+        assert!(!filter.is_match(Path::new("notes.txt")));
+        // Exclusions win over inclusions.
+        assert!(!filter.is_match(Path::new("slides/drafts/lecture2.pdf")));
+
+        assert!(PathFilter::new(&["[".to_string()], &[]).is_err());
+    }
+
+    #[test]
+    fn test_collect_input_files() {
+        let dir = TempDir::new("slideo_input_files").unwrap();
+        let root = dir.path();
+        create_dir_all(root.join("drafts")).unwrap();
+        write(root.join("lecture1.pdf"), b"%PDF-1.4").unwrap();
+        write(root.join("drafts/lecture2.pdf"), b"%PDF-1.4").unwrap();
+        write(root.join("notes.txt"), b"unsupported").unwrap();
+
+        let paths = |filter: &PathFilter| {
+            collect_input_files(&[root.to_path_buf()], filter)
+                .unwrap()
+                .into_iter()
+                .map(|p| p.path)
+                .collect::<Vec<_>>()
+        };
+
+        // Unsupported files are skipped, subdirectories are scanned.
+        assert_eq!(
+            paths(&filter(&[], &[])),
+            vec![root.join("drafts/lecture2.pdf"), root.join("lecture1.pdf")]
+        );
+        assert_eq!(
+            paths(&filter(&[], &["drafts/**"])),
+            vec![root.join("lecture1.pdf")]
+        );
+
+        // Explicitly passed files are never filtered or skipped.
+        let explicit =
+            collect_input_files(&[root.join("drafts/lecture2.pdf")], &filter(&[], &["**"]));
+        assert_eq!(explicit.unwrap().len(), 1);
+        assert!(collect_input_files(&[root.join("notes.txt")], &filter(&[], &[])).is_err());
+    }
+
+    #[test]
+    fn test_read_file_list() {
+        let dir = TempDir::new("slideo_file_list").unwrap();
+        let list_path = dir.path().join("files.txt");
+        write(
+            &list_path,
+            "# Lectures\nslides/lecture1.pdf\n\n  recordings/  \n/videos/week1.mp4\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            read_file_list(&list_path).unwrap(),
+            vec![
+                dir.path().join("slides/lecture1.pdf"),
+                dir.path().join("recordings/"),
+                PathBuf::from("/videos/week1.mp4"),
+            ]
+        );
+        assert!(read_file_list(&dir.path().join("missing.txt")).is_err());
+    }
+}
@@ -1,33 +1,118 @@
 mod checked_path;
 mod db;
-mod pdf_to_images;
+mod deck_to_images;
+mod decks;
+mod file_sniffing;
+mod input_files;
 mod progress;
+mod project;
+mod text_index;
+mod thumbnails;
+mod transcode;
 mod utils;
 mod video_exts;
 mod web;
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use checked_path::{CheckedPath, Kind};
 use db::{Db, DbPool};
+use deck_to_images::{decks_to_images, DeckPage};
 use dialoguer::Confirm;
+use input_files::{collect_input_files, read_file_list, PathFilter};
 use matching::ImageVideoMatcher;
-use matching_opencv::OpenCVImageVideoMatcher;
-use pdf_to_images::pdfs_to_images;
+use matching_opencv::{get_video_frame_size, Decoding, MatchingParams, OpenCVImageVideoMatcher};
+use pdftocairo::PageSelection;
 use progress::{ComposedProgressReporter, IndicatifProgressReporter};
+use project::{
+    read_matching_params, read_project, Detector, MatcherSettings, SamplingMode, Transformation,
+    DEFAULT_PROJECT_FILE,
+};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use std::collections::HashSet;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use structopt::StructOpt;
-use utils::hash_file;
+use text_index::index_pdf_texts;
+use thumbnails::store_thumbnails;
+use transcode::{
+    convert_for_browser, get_browser_conversion, get_cached_playable_video, Conversion,
+};
+use utils::{canonicalize_lenient, hash_file, hash_image_dir, FileStat, HashAlgorithm};
 use web::start_server;
 
 #[derive(StructOpt, Debug)]
 #[structopt(name = "slideo")]
 struct Opt {
-    /// A list of all videos and pdfs to process. If only a single pdf is passed, opens a viewer.
-    #[structopt(name = "FILES", parse(from_os_str), required = true)]
+    /// A list of all videos and pdfs to process. Directories are scanned recursively.
+    /// If only a single pdf is passed, opens a viewer.
+    /// If no files are given, the project manifest `slideo.toml` in the current directory is used.
+    #[structopt(name = "FILES", parse(from_os_str))]
     files: Vec<PathBuf>,
 
+    /// Processes the groups of pdfs and videos declared in the given project manifest.
+    /// Every group is matched independently.
+    #[structopt(
+        long,
+        parse(from_os_str),
+        conflicts_with_all = &["FILES", "files-from", "include", "exclude"]
+    )]
+    project: Option<PathBuf>,
+
+    /// Reads additional files and directories from the given file list, one path per line.
+    #[structopt(long, parse(from_os_str))]
+    files_from: Option<PathBuf>,
+
+    /// Only uses files from scanned directories that match one of these glob patterns.
+    #[structopt(long, number_of_values = 1)]
+    include: Vec<String>,
+
+    /// Ignores files from scanned directories that match one of these glob patterns.
+    #[structopt(long, number_of_values = 1)]
+    exclude: Vec<String>,
+
+    /// Only matches the selected pages of a pdf, e.g. `--pages slides.pdf=1-10,15,20-`.
+    /// Can be given once per pdf. Use `pages` in the project manifest for projects.
+    #[structopt(long, number_of_values = 1, conflicts_with = "project")]
+    pages: Vec<PdfPageSelection>,
+
+    /// The algorithm used to identify files: `sha256` or `blake3` (faster).
+    /// Switching algorithms changes the identity of all files, so their videos are processed again.
+    #[structopt(long, default_value = "sha256")]
+    hash_algorithm: HashAlgorithm,
+
+    /// How slides are mapped onto video frames: `similarity` (screen recordings, default)
+    /// or `homography` (cameras filming a projector screen at an angle).
+    /// Overrides `transformation` of the project manifest.
+    #[structopt(long)]
+    transformation: Option<Transformation>,
+
+    /// The keypoint detector: `orb` (default), `akaze`, `brisk` or `sift`
+    /// (slower, but more robust for low-resolution recordings).
+    /// Overrides `detector` of the project manifest.
+    #[structopt(long)]
+    detector: Option<Detector>,
+
+    /// Which frames are matched: `interval` (default) or `scenes`, which examines two frames per second
+    /// and only matches the first stable frame after every visual cut.
+    /// Overrides `sampling` of the project manifest.
+    #[structopt(long)]
+    sampling: Option<SamplingMode>,
+
+    /// Decodes every frame of the videos instead of seeking to the sampled frames.
+    /// Slower, but works around videos that cannot be seeked accurately.
+    #[structopt(long)]
+    sequential_decoding: bool,
+
+    /// Matches every frame on its own instead of smoothing the matched slides over time.
+    /// Overrides `smoothing` of the project manifest.
+    #[structopt(long)]
+    no_smoothing: bool,
+
+    /// Reads the matching thresholds from the given toml file,
+    /// e.g. one written by the `tune_params` example of `matching-opencv`.
+    #[structopt(long)]
+    matching_params: Option<PathBuf>,
+
     /// Invalidates any cached mapping entries that exist for the given files.
     #[structopt(long)]
     invalidate_video_cache: bool,
@@ -35,6 +120,48 @@ struct Opt {
     /// Does not wait for user input.
     #[structopt(long, short = "n")]
     non_interactive: bool,
+
+    /// Does not convert processed videos that browsers cannot play.
+    /// They are converted in the background when they are first opened in the viewer then.
+    #[structopt(long)]
+    no_video_conversion: bool,
+
+    #[structopt(subcommand)]
+    cmd: Option<Command>,
+}
+
+/// A `--pages` argument like `slides.pdf=1-10,15`.
+#[derive(Debug)]
+struct PdfPageSelection {
+    pdf: PathBuf,
+    pages: PageSelection,
+}
+
+impl FromStr for PdfPageSelection {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (pdf, pages) = s
+            .rsplit_once('=')
+            .ok_or_else(|| anyhow!("Expected '<pdf>=<pages>', e.g. 'slides.pdf=1-10,15'"))?;
+        Ok(PdfPageSelection {
+            pdf: PathBuf::from(pdf),
+            pages: pages.parse()?,
+        })
+    }
+}
+
+#[derive(StructOpt, Debug)]
+enum Command {
+    /// Updates the known paths of all files in a folder that has been moved.
+    Relocate {
+        /// The old location of the folder.
+        #[structopt(parse(from_os_str))]
+        from: PathBuf,
+        /// The new location of the folder.
+        #[structopt(parse(from_os_str))]
+        to: PathBuf,
+    },
 }
 
 #[async_std::main]
@@ -47,31 +174,161 @@ async fn main() -> Result<()> {
     let db_pool = DbPool::connect().await?;
     let mut db = db_pool.db().await?;
 
-    let (pdfs, videos) = process_files(&opt.files, &mut db).await?;
-    let videos_to_process = get_videos_to_process(&videos, &pdfs, &opt, &mut db).await?;
+    if let Some(Command::Relocate { from, to }) = &opt.cmd {
+        return relocate(from, to, &mut db).await;
+    }
+
+    let project = match &opt.project {
+        Some(project) => Some(project.clone()),
+        None if opt.files.is_empty() && opt.files_from.is_none() => {
+            if !opt.include.is_empty() || !opt.exclude.is_empty() || !opt.pages.is_empty() {
+                return Err(anyhow!(
+                    "--include, --exclude and --pages cannot be used with a project manifest!"
+                ));
+            }
+            let default_project = PathBuf::from(DEFAULT_PROJECT_FILE);
+            if !default_project.is_file() {
+                return Err(anyhow!(
+                    "No files given and no '{}' found in the current directory!",
+                    DEFAULT_PROJECT_FILE
+                ));
+            }
+            Some(default_project)
+        }
+        None => None,
+    };
+
+    if let Some(project) = project {
+        for mut group in read_project(&project)? {
+            println!("Processing group '{}'.", group.name);
+            let files = std::mem::take(&mut group.files);
+            let (pdfs, videos) = process_files(files, opt.hash_algorithm, &mut db).await?;
+            index_pdf_texts(&pdfs, &mut db).await?;
+            let page_selections = group.page_selections(pdfs.iter().map(|p| &p.hash as &str));
+            match_videos(
+                &pdfs,
+                &videos,
+                &group.matcher,
+                &page_selections,
+                &opt,
+                &db_pool,
+                &mut db,
+            )
+            .await?;
+        }
+        return Ok(());
+    }
+
+    let mut files = opt.files.clone();
+    if let Some(files_from) = &opt.files_from {
+        files.extend(read_file_list(files_from)?);
+    }
+    let filter = PathFilter::new(&opt.include, &opt.exclude)?;
+    let paths = collect_input_files(&files, &filter)?;
+
+    let (pdfs, videos) = process_files(paths, opt.hash_algorithm, &mut db).await?;
+    index_pdf_texts(&pdfs, &mut db).await?;
+    let page_selections = resolve_page_selections(&opt.pages, &pdfs)?;
+    match_videos(
+        &pdfs,
+        &videos,
+        &MatcherSettings::default(),
+        &page_selections,
+        &opt,
+        &db_pool,
+        &mut db,
+    )
+    .await?;
+
+    if !opt.non_interactive && pdfs.len() == 1 {
+        let first = pdfs.iter().next();
+        start_server(first.map(|h| h.hash.clone()))?;
+    }
+
+    Ok(())
+}
+
+/// Matches the pages of the given decks with all videos that need to be (re)processed.
+/// `page_selections` maps deck hashes to the pages that are considered, all pages of other decks are.
+async fn match_videos(
+    pdfs: &Vec<HashedFile>,
+    videos: &Vec<HashedFile>,
+    matcher_settings: &MatcherSettings,
+    page_selections: &HashMap<String, PageSelection>,
+    opt: &Opt,
+    db_pool: &DbPool,
+    db: &mut Db<'static>,
+) -> Result<()> {
+    let params = match &opt.matching_params {
+        Some(path) => read_matching_params(path)?,
+        None => MatchingParams::default(),
+    };
+    let videos_to_process = get_videos_to_process(videos, pdfs, opt, db).await?;
 
     if videos_to_process.len() > 0 {
+        // Matching works best if the pages are rendered at about the size they appear in the videos.
+        let render_size = videos_to_process
+            .iter()
+            .filter_map(|v| get_video_frame_size(&v.path))
+            .map(|(width, height)| width.max(height))
+            .max();
+
         let reporter = IndicatifProgressReporter::default();
-        let pages = pdfs_to_images(
+        let pages = decks_to_images(
             &pdfs.iter().map(|p| p).collect(),
-            &db_pool,
+            page_selections,
+            render_size,
+            db_pool,
             reporter.get_reporter(),
         )?;
         reporter.finish();
 
+        // Recorded with every video, so results can be related to the parameters they were matched with.
+        let params_json = serde_json::to_string(&params)?;
         let mut tx = db.begin_trans().await?;
         for video in &videos_to_process {
-            tx.create_or_reset_video(&video.hash, pdfs.iter().map(|v| &v.hash as &str))
-                .await?;
+            tx.create_or_reset_video(
+                &video.hash,
+                pdfs.iter().map(|v| &v.hash as &str),
+                &params_json,
+            )
+            .await?;
         }
         tx.commit().await?;
 
-        let matcher = OpenCVImageVideoMatcher::default();
+        let matcher = OpenCVImageVideoMatcher::new(matcher_settings.interval())
+            .with_sampling(matcher_settings.sampling(opt.sampling))
+            .with_decoding(if opt.sequential_decoding {
+                Decoding::Sequential
+            } else {
+                Decoding::Seek
+            })
+            .with_smoothing(matcher_settings.smoothing(opt.no_smoothing))
+            .with_preprocessing(matcher_settings.preprocessing())
+            .with_transformation_model(
+                opt.transformation
+                    .or(matcher_settings.transformation)
+                    .map(|t| t.model())
+                    .unwrap_or_default(),
+            )
+            .with_feature_extractor(matcher_settings.feature_extractor(opt.detector))
+            .with_params(params);
         let reporter = IndicatifProgressReporter::default();
         let video_matcher =
             matcher.create_video_matcher(pages.iter().collect(), reporter.get_reporter());
         reporter.finish();
 
+        let overlay_groups = overlay_groups_by_deck(&video_matcher.overlay_groups());
+        let mut tx = db.begin_trans().await?;
+        for pdf in pdfs {
+            let groups = overlay_groups
+                .get(&pdf.hash as &str)
+                .cloned()
+                .unwrap_or_default();
+            tx.set_pdf_overlay_groups(&pdf.hash, &groups).await?;
+        }
+        tx.commit().await?;
+
         let base_reporter = IndicatifProgressReporter::default();
         let reporter = ComposedProgressReporter::new(base_reporter.get_reporter());
         let tasks: Vec<_> = videos_to_process
@@ -86,22 +343,110 @@ async fn main() -> Result<()> {
 
         for (video, task) in tasks {
             let matchings = task.process();
+            store_thumbnails(&video.hash, &matchings).with_context(|| {
+                format!(
+                    "Could not store the thumbnails of '{}'",
+                    video.path.to_string_lossy()
+                )
+            })?;
             let mut tx = db.begin_trans().await?;
             tx.update_video_matchings(&video.hash, matchings.iter())
                 .await?;
             tx.commit().await?;
         }
         base_reporter.finish();
-    }
 
-    if !opt.non_interactive && pdfs.len() == 1 {
-        let first = pdfs.iter().next();
-        start_server(first.map(|h| h.hash.clone()))?;
+        if !opt.no_video_conversion {
+            for video in &videos_to_process {
+                if get_cached_playable_video(&video.path, &video.hash).is_some() {
+                    continue;
+                }
+                let conversion = match get_browser_conversion(&video.path) {
+                    Ok(Conversion::None) => continue,
+                    Ok(conversion) => conversion,
+                    Err(err) => {
+                        println!("Cannot convert video for the viewer: {:#}", err);
+                        continue;
+                    }
+                };
+                println!(
+                    "Converting video '{}' for the viewer...",
+                    video.path.to_string_lossy()
+                );
+                // The matchings are already stored, so a failed conversion does not abort the run.
+                if let Err(err) = convert_for_browser(&video.path, &video.hash, conversion) {
+                    println!("Cannot convert video for the viewer: {:#}", err);
+                }
+            }
+        }
     }
 
     Ok(())
 }
 
+async fn relocate(from: &Path, to: &Path, db: &mut Db<'static>) -> Result<()> {
+    // Known paths are canonical, but the old location might not exist anymore.
+    let from = canonicalize_lenient(from)?;
+    let to = to
+        .canonicalize()
+        .with_context(|| format!("Could not resolve '{}'", to.to_string_lossy()))?;
+
+    let mut tx = db.begin_trans().await?;
+    let relocated = tx.relocate_files(&from, &to).await?;
+    tx.commit().await?;
+
+    for (old_path, new_path) in &relocated {
+        println!(
+            "{} -> {}",
+            old_path.to_string_lossy(),
+            new_path.to_string_lossy()
+        );
+    }
+    if relocated.is_empty() {
+        println!(
+            "Warning: No known file is located in '{}'.",
+            from.to_string_lossy()
+        );
+    }
+    println!("Relocated {} files.", relocated.len());
+    Ok(())
+}
+
+/// Returns the 0-based pages of the overlay groups for each deck.
+/// The pages of an overlay group always belong to the same deck.
+fn overlay_groups_by_deck<'a>(groups: &[Vec<&DeckPage<'a>>]) -> HashMap<&'a str, Vec<Vec<u32>>> {
+    let mut result = HashMap::<&str, Vec<Vec<u32>>>::new();
+    for group in groups {
+        result
+            .entry(group[0].deck_hash)
+            .or_default()
+            .push(group.iter().map(|p| (p.page_nr - 1) as u32).collect());
+    }
+    result
+}
+
+/// Maps the hashes of the selected pdfs to their page selection.
+fn resolve_page_selections(
+    selections: &[PdfPageSelection],
+    pdfs: &[HashedFile],
+) -> Result<HashMap<String, PageSelection>> {
+    let mut result = HashMap::new();
+    for selection in selections {
+        let path = selection
+            .pdf
+            .canonicalize()
+            .with_context(|| format!("Could not find pdf '{}'", selection.pdf.to_string_lossy()))?;
+        let pdf = pdfs.iter().find(|p| p.path == path).ok_or_else(|| {
+            anyhow!(
+                "'{}' is not one of the given pdfs",
+                selection.pdf.to_string_lossy()
+            )
+        })?;
+        result.insert(pdf.hash.clone(), selection.pages.clone());
+    }
+    Ok(result)
+}
+
 pub fn add_to_path(path: PathBuf) -> Result<()> {
     let path_val = std::env::var("PATH")?;
     let mut paths: Vec<PathBuf> = std::env::split_paths(&path_val).collect();
@@ -115,37 +460,40 @@ pub fn add_to_path(path: PathBuf) -> Result<()> {
 pub struct HashedFile {
     pub path: PathBuf,
     pub hash: String,
+    pub kind: Kind,
 }
 
 impl HashedFile {
-    pub fn new(path: PathBuf, hash: String) -> HashedFile {
-        HashedFile { path, hash }
+    pub fn new(path: PathBuf, hash: String, kind: Kind) -> HashedFile {
+        HashedFile { path, hash, kind }
     }
 }
 
 async fn process_files(
-    files: &Vec<PathBuf>,
+    paths: Vec<CheckedPath>,
+    algorithm: HashAlgorithm,
     db: &mut Db<'static>,
 ) -> Result<(Vec<HashedFile>, Vec<HashedFile>)> {
-    let paths = get_files_with_hash(files)?;
+    let paths = get_files_with_hash(paths, algorithm, db).await?;
 
     let mut tx = db.begin_trans().await?;
     tx.update_hashes(
         paths
             .iter()
-            .map(|p| (p.path.as_ref(), p.hash.as_ref().unwrap() as &str)),
+            .map(|(p, stat)| (p.path.as_ref(), p.hash.as_ref().unwrap() as &str, stat)),
+        algorithm,
     )
     .await?;
     tx.commit().await?;
 
     let mut videos = Vec::<HashedFile>::new();
     let mut pdfs = Vec::<HashedFile>::new();
-    for path in paths {
+    for (path, _) in paths {
         let kind = path.kind;
-        let file = HashedFile::new(path.path, path.hash.unwrap());
+        let file = HashedFile::new(path.path, path.hash.unwrap(), kind);
         if kind == Kind::Video {
             videos.push(file);
-        } else if kind == Kind::Pdf {
+        } else {
             pdfs.push(file);
         }
     }
@@ -153,25 +501,59 @@ async fn process_files(
     Ok((pdfs, videos))
 }
 
-fn get_files_with_hash(files: &Vec<PathBuf>) -> Result<Vec<CheckedPath>> {
-    let paths = files
-        .iter()
-        .cloned()
-        .map(CheckedPath::from)
-        .collect::<Result<Vec<CheckedPath>>>()?;
+/// Computes the hashes of all given files.
+/// Hashes of files that did not change since they were last seen are taken from the database.
+async fn get_files_with_hash(
+    paths: Vec<CheckedPath>,
+    algorithm: HashAlgorithm,
+    db: &mut Db<'static>,
+) -> Result<Vec<(CheckedPath, FileStat)>> {
+    let mut stated_paths = Vec::new();
+    for p in paths {
+        let path = p
+            .path
+            .canonicalize()
+            .with_context(|| format!("Could not resolve file {}", p.path.to_string_lossy()))?;
+        let stat = FileStat::of(&path)
+            .with_context(|| format!("Could not read file {}", path.to_string_lossy()))?;
+        // The modification time of a directory does not change if the content of a file in it changes.
+        let hash = if p.kind == Kind::ImageDir {
+            None
+        } else {
+            db.find_cached_hash(&path, &stat, algorithm).await?
+        };
+        stated_paths.push((
+            CheckedPath {
+                hash,
+                kind: p.kind,
+                path,
+            },
+            stat,
+        ));
+    }
 
-    Ok(paths
+    Ok(stated_paths
         .into_par_iter()
-        .map(|p| {
-            let hash = hash_file(&p.path)
-                .with_context(|| format!("Could not hash file {}", p.path.to_string_lossy()))?;
-            Ok(CheckedPath {
-                hash: Some(hash),
-                kind: p.kind,
-                path: p.path,
-            })
+        .map(|(p, stat)| {
+            let hash = match p.hash {
+                Some(hash) => hash,
+                None if p.kind == Kind::ImageDir => hash_image_dir(&p.path, algorithm)
+                    .with_context(|| {
+                        format!("Could not hash directory {}", p.path.to_string_lossy())
+                    })?,
+                None => hash_file(&p.path, algorithm)
+                    .with_context(|| format!("Could not hash file {}", p.path.to_string_lossy()))?,
+            };
+            Ok((
+                CheckedPath {
+                    hash: Some(hash),
+                    kind: p.kind,
+                    path: p.path,
+                },
+                stat,
+            ))
         })
-        .collect::<Result<Vec<CheckedPath>>>()?)
+        .collect::<Result<Vec<_>>>()?)
 }
 
 async fn get_videos_to_process<'a>(
@@ -0,0 +1,492 @@
+use crate::{
+    checked_path::{CheckedPath, Kind},
+    decks::is_image_deck,
+    input_files::{collect_input_files, PathFilter},
+};
+use anyhow::{anyhow, Context, Result};
+use matching::SmoothingParams;
+use matching_opencv::{
+    FeatureExtractorConfig, MatchingParams, Preprocessing, PreprocessingStep, RelativeRect,
+    Sampling, TransformationModel,
+};
+use pdftocairo::PageSelection;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+/// The name of the project manifest that is used if no files are given.
+pub const DEFAULT_PROJECT_FILE: &str = "slideo.toml";
+
+/// A `slideo.toml` project manifest.
+///
+/// ```toml
+/// [[group]]
+/// name = "Week 1"
+/// pdfs = ["lecture1.pdf"]
+/// videos = ["week1/*.mp4"]
+/// # Excludes the appendix
+/// pages = "1-25,28"
+///
+/// [group.matcher]
+/// interval_secs = 2.5
+/// sampling = "scenes"
+/// smoothing = false
+/// # Ignores a webcam overlay in the bottom right corner
+/// mask = [{ x = 0.75, y = 0.75, width = 0.25, height = 0.25 }]
+/// crop_letterbox = true
+/// transformation = "homography"
+/// detector = "sift"
+/// features = 4000
+/// ```
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProjectManifest {
+    #[serde(default)]
+    group: Vec<GroupManifest>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GroupManifest {
+    name: Option<String>,
+    /// Paths or glob patterns, relative to the manifest.
+    pdfs: Vec<String>,
+    /// Paths or glob patterns, relative to the manifest.
+    videos: Vec<String>,
+    /// A page selection like `1-10,15,20-`.
+    pages: Option<String>,
+    #[serde(default)]
+    matcher: MatcherSettings,
+}
+
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct MatcherSettings {
+    /// Only every frame at this interval is matched.
+    pub interval_secs: Option<f64>,
+    /// Which frames are matched.
+    pub sampling: Option<SamplingMode>,
+    /// The interval at which frames are examined for visual cuts with `sampling = "scenes"`.
+    pub probe_interval_secs: Option<f64>,
+    /// Whether matchings are smoothed over time, which removes single misclassified frames.
+    pub smoothing: Option<bool>,
+    /// Regions of the video frames that never show slides.
+    #[serde(default)]
+    pub mask: Vec<RegionSettings>,
+    #[serde(default)]
+    pub crop_letterbox: bool,
+    #[serde(default)]
+    pub normalize_contrast: bool,
+    #[serde(default)]
+    pub deinterlace: bool,
+    /// How slides are mapped onto video frames.
+    pub transformation: Option<Transformation>,
+    /// The keypoint detector that is used to match slides with video frames.
+    pub detector: Option<Detector>,
+    /// The maximal number of keypoints per image, for `orb` and `sift`.
+    pub features: Option<i32>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Transformation {
+    /// Rotation, uniform scale and translation, for screen recordings.
+    Similarity,
+    /// A perspective transformation, for cameras that film a projector screen at an angle.
+    Homography,
+}
+
+impl Transformation {
+    pub fn model(&self) -> TransformationModel {
+        match self {
+            Transformation::Similarity => TransformationModel::Similarity,
+            Transformation::Homography => TransformationModel::Homography,
+        }
+    }
+}
+
+impl FromStr for Transformation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "similarity" => Ok(Transformation::Similarity),
+            "homography" => Ok(Transformation::Homography),
+            _ => Err(anyhow!(
+                "Unknown transformation '{}', expected 'similarity' or 'homography'",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SamplingMode {
+    /// A frame at every interval.
+    Interval,
+    /// The first stable frame after every visual cut.
+    Scenes,
+}
+
+impl FromStr for SamplingMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "interval" => Ok(SamplingMode::Interval),
+            "scenes" => Ok(SamplingMode::Scenes),
+            _ => Err(anyhow!(
+                "Unknown sampling mode '{}', expected 'interval' or 'scenes'",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Detector {
+    Orb,
+    Akaze,
+    Brisk,
+    /// Slower, but more robust for blurry, low-resolution recordings.
+    Sift,
+}
+
+impl FromStr for Detector {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "orb" => Ok(Detector::Orb),
+            "akaze" => Ok(Detector::Akaze),
+            "brisk" => Ok(Detector::Brisk),
+            "sift" => Ok(Detector::Sift),
+            _ => Err(anyhow!(
+                "Unknown detector '{}', expected 'orb', 'akaze', 'brisk' or 'sift'",
+                s
+            )),
+        }
+    }
+}
+
+/// A region in coordinates relative to the frame size, from 0 to 1.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RegionSettings {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl MatcherSettings {
+    /// Rejects settings that the matcher cannot work with.
+    fn validate(&self) -> Result<()> {
+        check_secs("interval_secs", self.interval_secs)?;
+        check_secs("probe_interval_secs", self.probe_interval_secs)?;
+        Ok(())
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs_f64(self.interval_secs.unwrap_or(5.0))
+    }
+
+    /// `mode` overrides the sampling mode of the settings.
+    pub fn sampling(&self, mode: Option<SamplingMode>) -> Sampling {
+        match mode.or(self.sampling).unwrap_or(SamplingMode::Interval) {
+            SamplingMode::Interval => Sampling::Interval,
+            SamplingMode::Scenes => Sampling::SceneChanges {
+                probe_interval: Duration::from_secs_f64(self.probe_interval_secs.unwrap_or(0.5)),
+            },
+        }
+    }
+
+    /// `no_smoothing` overrides the smoothing setting.
+    pub fn smoothing(&self, no_smoothing: bool) -> Option<SmoothingParams> {
+        if !no_smoothing && self.smoothing.unwrap_or(true) {
+            Some(SmoothingParams::default())
+        } else {
+            None
+        }
+    }
+
+    /// The frames are deinterlaced first, so that the mask refers to the entire frame.
+    pub fn preprocessing(&self) -> Preprocessing {
+        let mut steps = Vec::new();
+        if self.deinterlace {
+            steps.push(PreprocessingStep::Deinterlace);
+        }
+        if !self.mask.is_empty() {
+            steps.push(PreprocessingStep::MaskRegions(
+                self.mask
+                    .iter()
+                    .map(|r| RelativeRect {
+                        x: r.x,
+                        y: r.y,
+                        width: r.width,
+                        height: r.height,
+                    })
+                    .collect(),
+            ));
+        }
+        if self.crop_letterbox {
+            steps.push(PreprocessingStep::CropLetterbox);
+        }
+        if self.normalize_contrast {
+            steps.push(PreprocessingStep::NormalizeContrast);
+        }
+        Preprocessing { steps }
+    }
+
+    /// `detector` overrides the detector of the settings.
+    pub fn feature_extractor(&self, detector: Option<Detector>) -> FeatureExtractorConfig {
+        let mut config = match detector.or(self.detector).unwrap_or(Detector::Orb) {
+            Detector::Orb => FeatureExtractorConfig::orb(),
+            Detector::Akaze => FeatureExtractorConfig::akaze(),
+            Detector::Brisk => FeatureExtractorConfig::brisk(),
+            Detector::Sift => FeatureExtractorConfig::sift(),
+        };
+        if let Some(count) = self.features {
+            match &mut config {
+                FeatureExtractorConfig::Orb { features, .. }
+                | FeatureExtractorConfig::Sift { features, .. } => *features = count,
+                _ => {}
+            }
+        }
+        config
+    }
+}
+
+/// Durations must be finite and positive.
+fn check_secs(name: &str, secs: Option<f64>) -> Result<()> {
+    match secs {
+        Some(secs) if !(secs.is_finite() && secs > 0.0) => Err(anyhow!(
+            "'{}' must be a positive number of seconds, but is {}",
+            name,
+            secs
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// A set of pdfs and videos that is matched independently of all other groups.
+pub struct ProjectGroup {
+    pub name: String,
+    /// The pdfs and videos of this group.
+    pub files: Vec<CheckedPath>,
+    pub pages: Option<PageSelection>,
+    pub matcher: MatcherSettings,
+}
+
+impl ProjectGroup {
+    /// Maps the hashes of the given decks to the page selection of this group.
+    pub fn page_selections<'a>(
+        &self,
+        deck_hashes: impl Iterator<Item = &'a str>,
+    ) -> HashMap<String, PageSelection> {
+        match &self.pages {
+            Some(pages) => deck_hashes
+                .map(|h| (h.to_string(), pages.clone()))
+                .collect(),
+            None => HashMap::new(),
+        }
+    }
+}
+
+/// Reads matching parameters from a toml file, e.g. one written by the `tune_params` example.
+/// Missing parameters keep their defaults.
+pub fn read_matching_params(path: &Path) -> Result<MatchingParams> {
+    let content = read_to_string(path).with_context(|| {
+        format!(
+            "Could not read matching parameters '{}'",
+            path.to_string_lossy()
+        )
+    })?;
+    toml::from_str(&content).map_err(|err| {
+        anyhow!(
+            "Could not parse matching parameters '{}': {}",
+            path.to_string_lossy(),
+            err
+        )
+    })
+}
+
+pub fn read_project(manifest_path: &Path) -> Result<Vec<ProjectGroup>> {
+    let content = read_to_string(manifest_path).with_context(|| {
+        format!(
+            "Could not read project manifest '{}'",
+            manifest_path.to_string_lossy()
+        )
+    })?;
+    let manifest: ProjectManifest = toml::from_str(&content).map_err(|err| {
+        anyhow!(
+            "Could not parse project manifest '{}': {}",
+            manifest_path.to_string_lossy(),
+            err
+        )
+    })?;
+    let base_dir = manifest_path.parent().unwrap_or_else(|| Path::new(""));
+
+    manifest
+        .group
+        .into_iter()
+        .enumerate()
+        .map(|(idx, group)| {
+            let name = group.name.unwrap_or_else(|| format!("#{}", idx + 1));
+            let mut files = resolve_patterns(base_dir, &group.pdfs, Kind::Pdf)
+                .with_context(|| format!("Invalid pdfs in group '{}'", name))?;
+            files.extend(
+                resolve_patterns(base_dir, &group.videos, Kind::Video)
+                    .with_context(|| format!("Invalid videos in group '{}'", name))?,
+            );
+
+            let pages = group
+                .pages
+                .map(|p| p.parse::<PageSelection>())
+                .transpose()
+                .with_context(|| format!("Invalid pages in group '{}'", name))?;
+            group
+                .matcher
+                .validate()
+                .with_context(|| format!("Invalid matcher settings in group '{}'", name))?;
+
+            Ok(ProjectGroup {
+                name,
+                files,
+                pages,
+                matcher: group.matcher,
+            })
+        })
+        .collect()
+}
+
+/// Resolves the given patterns to files of the given kind.
+/// Directories are scanned recursively for files of that kind.
+fn resolve_patterns(base_dir: &Path, patterns: &[String], kind: Kind) -> Result<Vec<CheckedPath>> {
+    let mut paths = Vec::<PathBuf>::new();
+    for pattern in patterns {
+        let full_pattern = base_dir.join(pattern);
+        let matches = glob::glob(&full_pattern.to_string_lossy())
+            .with_context(|| format!("Invalid glob pattern '{}'", pattern))?
+            .collect::<Result<Vec<_>, _>>()?;
+        if matches.is_empty() {
+            return Err(anyhow!("'{}' does not match any file", pattern));
+        }
+        paths.extend(matches);
+    }
+
+    // Image decks are used like files.
+    let (dirs, files): (Vec<PathBuf>, Vec<PathBuf>) = paths
+        .into_iter()
+        .partition(|p| p.is_dir() && !is_image_deck(p));
+    // Pdfs also stand for the other kinds of decks.
+    let matches_kind = |k: Kind| {
+        if kind == Kind::Pdf {
+            k.is_deck()
+        } else {
+            k == kind
+        }
+    };
+
+    let mut result = collect_input_files(&files, &PathFilter::new(&[], &[])?)?;
+    if let Some(file) = result.iter().find(|f| !matches_kind(f.kind)) {
+        return Err(anyhow!(
+            "'{}' is not a {:?} file",
+            file.path.to_string_lossy(),
+            kind
+        ));
+    }
+
+    let dir_files = collect_input_files(&dirs, &PathFilter::new(&[], &[])?)?;
+    result.extend(dir_files.into_iter().filter(|f| matches_kind(f.kind)));
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, write};
+    use tempdir::TempDir;
+
+    const PDF: &[u8] = b"%PDF-1.5\n";
+    const MP4: &[u8] = b"\x00\x00\x00\x20ftypisom\x00\x00\x02\x00";
+
+    fn patterns(patterns: &[&str]) -> Vec<String> {
+        patterns.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn test_resolve_patterns() {
+        let dir = TempDir::new("slideo_project").unwrap();
+        let root = dir.path();
+        create_dir_all(root.join("week1/extra")).unwrap();
+        write(root.join("lecture1.pdf"), PDF).unwrap();
+        write(root.join("week1/a.mp4"), MP4).unwrap();
+        write(root.join("week1/extra/b.mp4"), MP4).unwrap();
+        write(root.join("week1/slides.pdf"), PDF).unwrap();
+
+        let paths = |files: Vec<CheckedPath>| files.into_iter().map(|f| f.path).collect::<Vec<_>>();
+
+        assert_eq!(
+            paths(resolve_patterns(root, &patterns(&["*.pdf"]), Kind::Pdf).unwrap()),
+            vec![root.join("lecture1.pdf")]
+        );
+        // Directories are scanned for files of the given kind only.
+        assert_eq!(
+            paths(resolve_patterns(root, &patterns(&["week1/"]), Kind::Video).unwrap()),
+            vec![root.join("week1/a.mp4"), root.join("week1/extra/b.mp4")]
+        );
+
+        assert!(resolve_patterns(root, &patterns(&["missing/*.mp4"]), Kind::Video).is_err());
+        // Explicitly matched files must have the given kind.
+        assert!(resolve_patterns(root, &patterns(&["lecture1.pdf"]), Kind::Video).is_err());
+    }
+
+    #[test]
+    fn test_validate_matcher_settings() {
+        assert!(MatcherSettings::default().validate().is_ok());
+
+        let settings = |interval_secs: f64| MatcherSettings {
+            interval_secs: Some(interval_secs),
+            ..MatcherSettings::default()
+        };
+        assert!(settings(2.5).validate().is_ok());
+        assert!(settings(0.0).validate().is_err());
+        assert!(settings(-1.0).validate().is_err());
+        assert!(settings(f64::NAN).validate().is_err());
+
+        let settings = MatcherSettings {
+            probe_interval_secs: Some(f64::INFINITY),
+            ..MatcherSettings::default()
+        };
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_page_selections() {
+        let group = |pages: Option<&str>| ProjectGroup {
+            name: "Week 1".to_string(),
+            files: Vec::new(),
+            pages: pages.map(|p| p.parse().unwrap()),
+            matcher: MatcherSettings::default(),
+        };
+        let decks = ["a", "b"];
+
+        assert!(group(None)
+            .page_selections(decks.iter().copied())
+            .is_empty());
+
+        let selections = group(Some("1-3,5,8-")).page_selections(decks.iter().copied());
+        assert_eq!(selections.len(), 2);
+        let selection = &selections["b"];
+        assert_eq!(selection.pages(10), vec![1, 2, 3, 5, 8, 9, 10]);
+    }
+}
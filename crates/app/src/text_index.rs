@@ -0,0 +1,35 @@
+use crate::{checked_path::Kind, db::Db, decks::get_deck_pdf, HashedFile};
+use anyhow::Result;
+use pdftocairo::{pdf_to_text, ExtractOptions, ProgressInfo};
+
+/// Extracts and indexes the text of all decks that have not been indexed yet.
+/// Image decks and decks whose text cannot be extracted are skipped.
+pub async fn index_pdf_texts(pdfs: &[HashedFile], db: &mut Db<'static>) -> Result<()> {
+    for pdf in pdfs {
+        if pdf.kind == Kind::ImageDir || db.is_pdf_text_indexed(&pdf.hash).await? {
+            continue;
+        }
+
+        let page_texts = match get_deck_pdf(&pdf.path, &pdf.hash, pdf.kind).and_then(|pdf_path| {
+            Ok(pdf_to_text(
+                &pdf_path,
+                ExtractOptions::<fn(ProgressInfo)>::default(),
+            )?)
+        }) {
+            Ok(page_texts) => page_texts,
+            Err(err) => {
+                println!(
+                    "Could not extract the text of '{}': {}",
+                    pdf.path.to_string_lossy(),
+                    err
+                );
+                continue;
+            }
+        };
+
+        let mut tx = db.begin_trans().await?;
+        tx.set_pdf_page_texts(&pdf.hash, &page_texts).await?;
+        tx.commit().await?;
+    }
+    Ok(())
+}
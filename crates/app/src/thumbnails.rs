@@ -0,0 +1,107 @@
+use crate::utils::get_temp_path_key;
+use anyhow::{Context, Result};
+use matching::Matching;
+use matching_opencv::create_sprite_sheet;
+use std::{
+    fmt::Write,
+    fs::{create_dir_all, remove_dir_all, write},
+    path::PathBuf,
+    time::Duration,
+};
+
+const SPRITE_COLUMNS: usize = 10;
+pub const SPRITE_FILE_NAME: &str = "sprite.jpg";
+pub const VTT_FILE_NAME: &str = "thumbnails.vtt";
+
+/// The directory that contains the thumbnails of all segments of the given video,
+/// named by their offset in milliseconds, the sprite sheet and its WebVTT track.
+pub fn get_thumbnail_dir(video_hash: &str) -> PathBuf {
+    get_temp_path_key("thumbnails", video_hash)
+}
+
+/// Writes the thumbnails of the given matchings, a sprite sheet and a WebVTT thumbnail track.
+/// `matchings` must be sorted by video time.
+pub fn store_thumbnails<I: Clone>(video_hash: &str, matchings: &[Matching<I>]) -> Result<()> {
+    let dir = get_thumbnail_dir(video_hash);
+    if dir.exists() {
+        remove_dir_all(&dir)?;
+    }
+    create_dir_all(&dir)?;
+
+    // The end of the last segment is the time of the following matching.
+    let segments: Vec<(&Vec<u8>, Duration, Duration)> = matchings
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, m)| {
+            let end = matchings
+                .get(idx + 1)
+                .map_or(m.video_time, |next| next.video_time);
+            m.thumbnail.as_ref().map(|t| (t, m.video_time, end))
+        })
+        .collect();
+
+    for (thumbnail, start, _) in &segments {
+        write(dir.join(format!("{}.jpg", start.as_millis())), thumbnail)?;
+    }
+
+    let sprite = create_sprite_sheet(
+        &segments
+            .iter()
+            .map(|(t, _, _)| t.as_slice())
+            .collect::<Vec<_>>(),
+        SPRITE_COLUMNS,
+    )
+    .context("Could not create the thumbnail sprite sheet")?;
+    write(dir.join(SPRITE_FILE_NAME), &sprite.jpeg)?;
+
+    let mut vtt = String::from("WEBVTT\n");
+    for (idx, (_, start, end)) in segments.iter().enumerate() {
+        let (x, y) = sprite.tile_position(idx);
+        write!(
+            vtt,
+            "\n{} --> {}\n{}#xywh={},{},{},{}\n",
+            format_vtt_time(*start),
+            format_vtt_time(*end),
+            SPRITE_FILE_NAME,
+            x,
+            y,
+            sprite.tile_width,
+            sprite.tile_height
+        )?;
+    }
+    write(dir.join(VTT_FILE_NAME), vtt)?;
+
+    Ok(())
+}
+
+fn format_vtt_time(time: Duration) -> String {
+    let ms = time.as_millis();
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        (ms / 60_000) % 60,
+        (ms / 1000) % 60,
+        ms % 1000
+    )
+}
+
+/// Finds the thumbnail of the segment that contains the given offset.
+pub fn find_thumbnail(video_hash: &str, ms: u64) -> Result<Option<PathBuf>> {
+    let dir = get_thumbnail_dir(video_hash);
+    if !dir.exists() {
+        return Ok(None);
+    }
+
+    let mut best: Option<(u64, PathBuf)> = None;
+    for entry in dir.read_dir()? {
+        let path = entry?.path();
+        let start_ms: u64 = match path.file_stem().and_then(|s| s.to_str()?.parse().ok()) {
+            Some(start_ms) => start_ms,
+            None => continue,
+        };
+        if start_ms <= ms && best.as_ref().is_none_or(|(b, _)| *b < start_ms) {
+            best = Some((start_ms, path));
+        }
+    }
+    Ok(best.map(|(_, path)| path))
+}
@@ -0,0 +1,241 @@
+use crate::utils::get_temp_path_key;
+use anyhow::{anyhow, Context, Result};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{create_dir_all, rename},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+/// Describes how a video has to be converted so that browsers can play it.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Conversion {
+    /// The video can be served as is.
+    None,
+    /// The streams are browser compatible, only the container has to be changed.
+    Remux(Container),
+    /// The streams have to be encoded again.
+    Transcode(Container),
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Container {
+    Mp4,
+    WebM,
+}
+
+impl Container {
+    fn ext(&self) -> &'static str {
+        match self {
+            Container::Mp4 => "mp4",
+            Container::WebM => "webm",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ProbeResult {
+    streams: Vec<ProbeStream>,
+    format: ProbeFormat,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ProbeFormat {
+    format_name: String,
+}
+
+/// Uses `ffprobe` to find out whether browsers can play the given video.
+pub fn get_browser_conversion(video: &Path) -> Result<Conversion> {
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-of", "json"])
+        .args([
+            "-show_entries",
+            "stream=codec_type,codec_name:format=format_name",
+        ])
+        .arg(video)
+        .output()
+        .context("Could not run ffprobe. Is ffmpeg installed?")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "ffprobe failed for '{}': {}",
+            video.to_string_lossy(),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    let probe: ProbeResult = serde_json::from_slice(&output.stdout)?;
+
+    let codecs_of = |codec_type: &str| -> Vec<&str> {
+        probe
+            .streams
+            .iter()
+            .filter(|s| s.codec_type.as_deref() == Some(codec_type))
+            .filter_map(|s| s.codec_name.as_deref())
+            .collect()
+    };
+    let video_codecs = codecs_of("video");
+    let audio_codecs = codecs_of("audio");
+
+    let all_in = |codecs: &[&str], supported: &[&str]| codecs.iter().all(|c| supported.contains(c));
+    let ext = video
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    // ffprobe reports all ISO base media files as "mov,mp4,m4a,3gp,3g2,mj2"
+    // and both matroska and webm as "matroska,webm".
+    let is_mp4 = probe.format.format_name.contains("mp4") && ext != "mov";
+    let is_webm = probe.format.format_name.contains("webm") && ext == "webm";
+
+    if all_in(&video_codecs, &["h264"]) && all_in(&audio_codecs, &["aac", "mp3"]) {
+        if is_mp4 {
+            return Ok(Conversion::None);
+        }
+        return Ok(Conversion::Remux(Container::Mp4));
+    }
+    if all_in(&video_codecs, &["vp8", "vp9", "av1"]) && all_in(&audio_codecs, &["opus", "vorbis"]) {
+        if is_webm {
+            return Ok(Conversion::None);
+        }
+        return Ok(Conversion::Remux(Container::WebM));
+    }
+    Ok(Conversion::Transcode(Container::Mp4))
+}
+
+lazy_static! {
+    /// Prevents that the same video is converted concurrently.
+    static ref CONVERSION_LOCKS: Mutex<HashMap<String, Arc<Mutex<()>>>> = Mutex::new(HashMap::new());
+    /// The hashes of the videos that have been probed and can be served as they are.
+    static ref PLAYABLE_VIDEOS: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+    /// The hashes of the videos that are being converted in the background.
+    static ref BACKGROUND_CONVERSIONS: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+fn get_target_path(video_hash: &str, container: Container) -> PathBuf {
+    get_temp_path_key("transcoded", video_hash).join(format!("video.{}", container.ext()))
+}
+
+/// Returns a path of the video with the given hash that browsers can play,
+/// if it is known without probing or converting the video.
+pub fn get_cached_playable_video(video: &Path, video_hash: &str) -> Option<PathBuf> {
+    if PLAYABLE_VIDEOS.lock().unwrap().contains(video_hash) {
+        return Some(video.to_owned());
+    }
+    [Container::Mp4, Container::WebM]
+        .iter()
+        .map(|&container| get_target_path(video_hash, container))
+        .find(|target| target.exists())
+}
+
+/// Returns a path of the video with the given hash that browsers can play.
+/// Converts the video into the cache directory if required.
+/// This can take a long time for videos that need to be transcoded.
+pub fn get_browser_playable_video(video: &Path, video_hash: &str) -> Result<PathBuf> {
+    if let Some(path) = get_cached_playable_video(video, video_hash) {
+        return Ok(path);
+    }
+    let conversion = get_browser_conversion(video)?;
+    convert_for_browser(video, video_hash, conversion)
+}
+
+/// Converts the video in a background thread, unless it is already being converted.
+/// Errors are logged.
+pub fn start_browser_conversion(video: PathBuf, video_hash: String) {
+    if !BACKGROUND_CONVERSIONS
+        .lock()
+        .unwrap()
+        .insert(video_hash.clone())
+    {
+        return;
+    }
+    thread::spawn(move || {
+        if let Err(err) = get_browser_playable_video(&video, &video_hash) {
+            println!("Cannot convert video for the viewer: {:#}", err);
+        }
+        BACKGROUND_CONVERSIONS.lock().unwrap().remove(&video_hash);
+    });
+}
+
+/// Whether the video with the given hash is being converted in the background.
+pub fn is_converting(video_hash: &str) -> bool {
+    BACKGROUND_CONVERSIONS.lock().unwrap().contains(video_hash)
+}
+
+/// Applies the given conversion, which `get_browser_conversion` returned for the video.
+/// The converted video is cached.
+pub fn convert_for_browser(
+    video: &Path,
+    video_hash: &str,
+    conversion: Conversion,
+) -> Result<PathBuf> {
+    let container = match conversion {
+        Conversion::None => {
+            PLAYABLE_VIDEOS
+                .lock()
+                .unwrap()
+                .insert(video_hash.to_owned());
+            return Ok(video.to_owned());
+        }
+        Conversion::Remux(container) | Conversion::Transcode(container) => container,
+    };
+
+    let lock = CONVERSION_LOCKS
+        .lock()
+        .unwrap()
+        .entry(video_hash.to_owned())
+        .or_default()
+        .clone();
+    let _guard = lock.lock().unwrap();
+
+    let target = get_target_path(video_hash, container);
+    let target_dir = target.parent().unwrap();
+    if target.exists() {
+        return Ok(target);
+    }
+    create_dir_all(target_dir)?;
+
+    // Write to a temporary file first, so that aborted conversions are not used.
+    let partial_target = target_dir.join(format!("video.part.{}", container.ext()));
+    let mut cmd = Command::new("ffmpeg");
+    cmd.args(["-v", "error", "-y", "-i"]).arg(video);
+    match conversion {
+        Conversion::Remux(_) => {
+            cmd.args(["-c", "copy"]);
+        }
+        Conversion::Transcode(Container::Mp4) => {
+            cmd.args(["-c:v", "libx264", "-preset", "veryfast", "-crf", "23"]);
+            cmd.args(["-pix_fmt", "yuv420p", "-c:a", "aac"]);
+        }
+        Conversion::Transcode(Container::WebM) => {
+            cmd.args(["-c:v", "libvpx-vp9", "-c:a", "libopus"]);
+        }
+        Conversion::None => unreachable!(),
+    }
+    if container == Container::Mp4 {
+        // Allows the browser to start playing before the entire file is loaded.
+        cmd.args(["-movflags", "+faststart"]);
+    }
+    cmd.arg(&partial_target);
+
+    let output = cmd
+        .output()
+        .context("Could not run ffmpeg. Is ffmpeg installed?")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "ffmpeg failed to convert '{}': {}",
+            video.to_string_lossy(),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    rename(&partial_target, &target)?;
+
+    Ok(target)
+}
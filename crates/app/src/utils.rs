@@ -1,8 +1,11 @@
-use anyhow::Result;
+use crate::decks::list_deck_images;
+use anyhow::{anyhow, Result};
 use std::{
     fs::File,
-    io::copy,
-    path::{Path, PathBuf},
+    io::{self, copy, Write},
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+    time::UNIX_EPOCH,
 };
 
 use sha2::{Digest, Sha256};
@@ -25,9 +28,153 @@ pub fn get_temp_path_key(category: &str, key: &str) -> PathBuf {
     return get_temp_path().join(&format!("{}-{}", category, &hash(key)[0..20]));
 }
 
-pub fn hash_file(path: &Path) -> Result<String> {
+/// Makes the path absolute like `canonicalize`, but also for paths that do not exist (anymore).
+/// Then the longest existing ancestor is canonicalized and `.` and `..` are resolved lexically.
+pub fn canonicalize_lenient(path: &Path) -> Result<PathBuf> {
+    if let Ok(path) = path.canonicalize() {
+        return Ok(path);
+    }
+
+    let mut normalized = PathBuf::new();
+    for component in std::env::current_dir()?.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    for ancestor in normalized.ancestors() {
+        if let Ok(canonical_ancestor) = ancestor.canonicalize() {
+            return Ok(canonical_ancestor.join(normalized.strip_prefix(ancestor)?));
+        }
+    }
+    Ok(normalized)
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    /// The name that is stored in the database.
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Blake3 => "blake3",
+        }
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            "blake3" => Ok(HashAlgorithm::Blake3),
+            _ => Err(anyhow!(
+                "Unknown hash algorithm '{}', expected 'sha256' or 'blake3'",
+                s
+            )),
+        }
+    }
+}
+
+pub fn hash_file(path: &Path, algorithm: HashAlgorithm) -> Result<String> {
     let mut file = File::open(path)?;
-    let mut sha256 = Sha256::new();
-    copy(&mut file, &mut sha256)?;
-    Ok(format!("{:x}", sha256.finalize()))
+    hash_content(algorithm, |hasher| {
+        copy(&mut file, hasher)?;
+        Ok(())
+    })
+}
+
+/// Hashes the images of an image deck in page order.
+/// The file names are included, since they define the order of the pages.
+pub fn hash_image_dir(dir: &Path, algorithm: HashAlgorithm) -> Result<String> {
+    let images = list_deck_images(dir)?;
+    hash_content(algorithm, |hasher| {
+        for image in &images {
+            hasher.write_all(image.file_name().unwrap().to_string_lossy().as_bytes())?;
+            hasher.write_all(&[0])?;
+            copy(&mut File::open(image)?, hasher)?;
+        }
+        Ok(())
+    })
+}
+
+fn hash_content(
+    algorithm: HashAlgorithm,
+    write_content: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> Result<String> {
+    match algorithm {
+        HashAlgorithm::Sha256 => {
+            let mut sha256 = Sha256::new();
+            write_content(&mut sha256)?;
+            Ok(format!("{:x}", sha256.finalize()))
+        }
+        HashAlgorithm::Blake3 => {
+            let mut blake3 = blake3::Hasher::new();
+            write_content(&mut blake3)?;
+            Ok(blake3::Hasher::finalize(&blake3).to_hex().to_string())
+        }
+    }
+}
+
+/// The file metadata that is used to decide whether a cached hash can be reused.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FileStat {
+    pub size: i64,
+    pub modified_ms: i64,
+    pub inode: Option<i64>,
+}
+
+impl FileStat {
+    pub fn of(path: &Path) -> io::Result<FileStat> {
+        let metadata = path.metadata()?;
+        let modified_ms = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as i64);
+
+        #[cfg(unix)]
+        let inode = {
+            use std::os::unix::fs::MetadataExt;
+            Some(metadata.ino() as i64)
+        };
+        #[cfg(not(unix))]
+        let inode = None;
+
+        Ok(FileStat {
+            size: metadata.len() as i64,
+            modified_ms,
+            inode,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::create_dir_all;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_canonicalize_lenient() {
+        let dir = TempDir::new("slideo_utils").unwrap();
+        create_dir_all(dir.path().join("lectures")).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+
+        assert_eq!(
+            canonicalize_lenient(&dir.path().join("lectures/./")).unwrap(),
+            root.join("lectures")
+        );
+        assert_eq!(
+            canonicalize_lenient(&dir.path().join("lectures/old/../moved/week1")).unwrap(),
+            root.join("lectures/moved/week1")
+        );
+    }
 }
@@ -1,375 +1,49 @@
 use lazy_static::lazy_static;
 use std::collections::HashSet;
 
+/// Checks if the lowercase extension belongs to a video container that OpenCV can decode with ffmpeg.
 pub fn is_video_ext(ext: &str) -> bool {
     VIDEO_EXTS.contains(ext)
 }
 
+/// Checks if the lowercase extension belongs to an audio-only format.
+/// OpenCV cannot extract frames from such files.
+pub fn is_audio_ext(ext: &str) -> bool {
+    AUDIO_EXTS.contains(ext)
+}
+
+/// Checks if the lowercase extension belongs to an image format that can be used as a slide.
+pub fn is_image_ext(ext: &str) -> bool {
+    IMAGE_EXTS.contains(ext)
+}
+
+/// Checks if the lowercase extension belongs to a presentation that LibreOffice can convert to pdf.
+pub fn is_presentation_ext(ext: &str) -> bool {
+    PRESENTATION_EXTS.contains(ext)
+}
+
 lazy_static! {
     static ref VIDEO_EXTS: HashSet<&'static str> = vec![
-        "3dostr",
-        "3g2",
-        "3gp",
-        "4xm",
-        "a64",
-        "aa",
-        "aac",
-        "ac3",
-        "acm",
-        "act",
-        "adf",
-        "adp",
-        "ads",
-        "adts",
-        "adx",
-        "aea",
-        "afc",
-        "aiff",
-        "aix",
-        "alaw",
-        "alias_pix",
-        "alp",
-        "amr",
-        "amrnb",
-        "amrwb",
-        "anm",
-        "apc",
-        "ape",
-        "apm",
-        "apng",
-        "aptx",
-        "aptx_hd",
-        "aqtitle",
-        "argo_asf",
-        "asf",
-        "asf_o",
-        "asf_stream",
-        "ass",
-        "ast",
-        "au",
-        "av1",
-        "avi",
-        "avisynth",
-        "avm2",
-        "avr",
-        "avs",
-        "avs2",
-        "bethsoftvid",
-        "bfi",
-        "bfstm",
-        "bin",
-        "bink",
-        "bit",
-        "bmp_pipe",
-        "bmv",
-        "boa",
-        "brender_pix",
-        "brstm",
-        "c93",
-        "caf",
-        "cavsvideo",
-        "cdg",
-        "cdxl",
-        "cine",
-        "codec2",
-        "codec2raw",
-        "concat",
-        "crc",
-        "dash",
-        "data",
-        "daud",
-        "dcstr",
-        "dds_pipe",
-        "derf",
-        "dfa",
-        "dhav",
-        "dirac",
-        "dnxhd",
-        "dpx_pipe",
-        "dsf",
-        "dshow",
-        "dsicin",
-        "dss",
-        "dts",
-        "dtshd",
-        "dv",
-        "dvbsub",
-        "dvbtxt",
-        "dvd",
-        "dxa",
-        "ea",
-        "ea_cdata",
-        "eac3",
-        "epaf",
-        "exr_pipe",
-        "f32be",
-        "f32le",
-        "f4v",
-        "f64be",
-        "f64le",
-        "ffmetadata",
-        "fifo",
-        "fifo_test",
-        "film_cpk",
-        "filmstrip",
-        "fits",
-        "flac",
-        "flic",
-        "flv",
-        "framecrc",
-        "framehash",
-        "framemd5",
-        "frm",
-        "fsb",
-        "fwse",
-        "g722",
-        "g723_1",
-        "g726",
-        "g726le",
-        "g729",
-        "gdigrab",
-        "gdv",
-        "genh",
-        "gif",
-        "gif_pipe",
-        "gsm",
-        "gxf",
-        "h261",
-        "h263",
-        "h264",
-        "hash",
-        "hca",
-        "hcom",
-        "hds",
-        "hevc",
-        "hls",
-        "hnm",
-        "ico",
-        "idcin",
-        "idf",
-        "iff",
-        "ifv",
-        "ilbc",
-        "image2",
-        "image2pipe",
-        "ingenient",
-        "ipmovie",
-        "ipod",
-        "ircam",
-        "ismv",
-        "iss",
-        "iv8",
-        "ivf",
-        "ivr",
-        "j2k_pipe",
-        "jacosub",
-        "jpeg_pipe",
-        "jpegls_pipe",
-        "jv",
-        "kux",
-        "kvag",
-        "latm",
-        "lavfi",
-        "libgme",
-        "libopenmpt",
-        "live_flv",
-        "lmlm4",
-        "loas",
-        "lrc",
-        "lvf",
-        "lxf",
-        "m4v",
-        "matroska",
-        "matroska",
-        "md5",
-        "mgsts",
-        "microdvd",
-        "mjpeg",
-        "mjpeg_2000",
-        "mkvtimestamp_v2",
-        "mlp",
-        "mlv",
-        "mm",
-        "mmf",
-        "mov",
-        "mov",
-        "mp2",
-        "mp3",
-        "mp4",
-        "mpc",
-        "mpc8",
-        "mpeg",
-        "mpeg1video",
-        "mpeg2video",
-        "mpegts",
-        "mpegtsraw",
-        "mpegvideo",
-        "mpjpeg",
-        "mpl2",
-        "mpsub",
-        "msf",
-        "msnwctcp",
-        "mtaf",
-        "mtv",
-        "mulaw",
-        "musx",
-        "mv",
-        "mvi",
-        "mxf",
-        "mxf_d10",
-        "mxf_opatom",
-        "mxg",
-        "nc",
-        "nistsphere",
-        "nsp",
-        "nsv",
-        "null",
-        "nut",
-        "nuv",
-        "oga",
-        "ogg",
-        "ogv",
-        "oma",
-        "opus",
-        "paf",
-        "pam_pipe",
-        "pbm_pipe",
-        "pcx_pipe",
-        "pgm_pipe",
-        "pgmyuv_pipe",
-        "pictor_pipe",
-        "pjs",
-        "pmp",
-        "png_pipe",
-        "pp_bnk",
-        "ppm_pipe",
-        "psd_pipe",
-        "psp",
-        "psxstr",
-        "pva",
-        "pvf",
-        "qcp",
-        "qdraw_pipe",
-        "r3d",
-        "rawvideo",
-        "realtext",
-        "redspark",
-        "rl2",
-        "rm",
-        "roq",
-        "rpl",
-        "rsd",
-        "rso",
-        "rtp",
-        "rtp_mpegts",
-        "rtsp",
-        "s16be",
-        "s16le",
-        "s24be",
-        "s24le",
-        "s32be",
-        "s32le",
-        "s337m",
-        "s8",
-        "sami",
-        "sap",
-        "sbc",
-        "sbg",
-        "scc",
-        "sdl",
-        "sdp",
-        "sdr2",
-        "sds",
-        "sdx",
-        "segment",
-        "ser",
-        "sgi_pipe",
-        "shn",
-        "siff",
-        "singlejpeg",
-        "sln",
-        "smjpeg",
-        "smk",
-        "smoothstreaming",
-        "smush",
-        "sol",
-        "sox",
-        "spdif",
-        "spx",
-        "srt",
-        "stl",
-        "stream_segment",
-        "streamhash",
-        "subviewer",
-        "subviewer1",
-        "sunrast_pipe",
-        "sup",
-        "svag",
-        "svcd",
-        "svg_pipe",
-        "swf",
-        "tak",
-        "tedcaptions",
-        "tee",
-        "thp",
-        "tiertexseq",
-        "tiff_pipe",
-        "tmv",
-        "truehd",
-        "tta",
-        "tty",
-        "txd",
-        "ty",
-        "u16be",
-        "u16le",
-        "u24be",
-        "u24le",
-        "u32be",
-        "u32le",
-        "u8",
-        "uncodedframecrc",
-        "v210",
-        "v210x",
-        "vag",
-        "vc1",
-        "vc1test",
-        "vcd",
-        "vfwcap",
-        "vidc",
-        "vividas",
-        "vivo",
-        "vmd",
-        "vob",
-        "vobsub",
-        "voc",
-        "vpk",
-        "vplayer",
-        "vqf",
-        "w64",
-        "wav",
-        "wc3movie",
-        "webm",
-        "webm_chunk",
-        "webm_dash_manifest",
-        "webp",
-        "webp_pipe",
-        "webvtt",
-        "wsaud",
-        "wsd",
-        "wsvqa",
-        "wtv",
-        "wv",
-        "wve",
-        "xa",
-        "xbin",
-        "xmv",
-        "xpm_pipe",
-        "xvag",
-        "xwd_pipe",
-        "xwma",
-        "yop",
-        "yuv4mpegpipe",
+        "3g2", "3gp", "asf", "avi", "divx", "dv", "f4v", "flv", "h264", "h265", "hevc", "ivf",
+        "m1v", "m2t", "m2ts", "m2v", "m4v", "mjpeg", "mkv", "mov", "mp4", "mpe", "mpeg", "mpg",
+        "mts", "mxf", "nut", "nuv", "ogm", "ogv", "qt", "rm", "rmvb", "swf", "ts", "vob", "webm",
+        "wmv", "wtv", "y4m",
+    ]
+    .into_iter()
+    .collect();
+    static ref AUDIO_EXTS: HashSet<&'static str> = vec![
+        "aac", "ac3", "aif", "aifc", "aiff", "amr", "ape", "au", "caf", "dts", "eac3", "flac",
+        "m4a", "m4b", "m4p", "mka", "mp2", "mp3", "mpc", "oga", "ogg", "opus", "ra", "spx", "tak",
+        "tta", "voc", "w64", "wav", "weba", "wma", "wv",
     ]
     .into_iter()
     .collect();
+    static ref IMAGE_EXTS: HashSet<&'static str> =
+        vec!["bmp", "jpeg", "jpg", "png", "tif", "tiff", "webp"]
+            .into_iter()
+            .collect();
+    static ref PRESENTATION_EXTS: HashSet<&'static str> =
+        vec!["fodp", "key", "odp", "pps", "ppsx", "ppt", "pptx"]
+            .into_iter()
+            .collect();
 }
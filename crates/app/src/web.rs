@@ -1,23 +1,76 @@
-use crate::db::{DbPool, PdfVideoMatching};
+use crate::{
+    checked_path::{CheckedPath, Kind},
+    db::{collapse_overlays, DbPool, PageSearchHit, PdfVideoMatching},
+    deck_to_images::render_page_preview,
+    decks::convert_presentation,
+    thumbnails::{find_thumbnail, get_thumbnail_dir, SPRITE_FILE_NAME, VTT_FILE_NAME},
+    transcode::{get_cached_playable_video, is_converting, start_browser_conversion},
+};
 use actix_cors::Cors;
 use actix_files::NamedFile;
 use actix_web::{
     body::Body,
+    error::BlockingError,
     get,
+    http::StatusCode,
     web::{self, Json},
     App, HttpServer,
 };
 use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use pdftocairo::read_pages;
 use rust_embed::RustEmbed;
-use std::borrow::Cow;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, collections::HashMap, path::Path, sync::Mutex};
+
+/// The size of rendered pages, if the pages extracted for matching cannot be used.
+const DEFAULT_PAGE_SIZE: u32 = 1280;
+/// Larger requested sizes are reduced to this, so that requests cannot make poppler render huge images.
+const MAX_PAGE_SIZE: u32 = 4096;
+/// Requested sizes are rounded up to one of these, so that only a few previews are cached per page.
+const PAGE_SIZE_BUCKETS: [u32; 5] = [256, 512, 1024, 2048, MAX_PAGE_SIZE];
+
+fn round_page_size(size: u32) -> u32 {
+    PAGE_SIZE_BUCKETS
+        .iter()
+        .copied()
+        .find(|&bucket| bucket >= size)
+        .unwrap_or(MAX_PAGE_SIZE)
+}
 
 struct AppState {
     db_pool: DbPool,
 }
 
+lazy_static! {
+    /// The kinds of the served files by their hash.
+    static ref FILE_KINDS: Mutex<HashMap<String, Kind>> = Mutex::new(HashMap::new());
+}
+
+/// Returns the kind of the file with the given hash.
+/// Kinds are cached, so that files are not sniffed again on every (range) request.
+fn get_file_kind(path: &Path, hash: &str) -> Result<Kind> {
+    if let Some(kind) = FILE_KINDS.lock().unwrap().get(hash) {
+        return Ok(*kind);
+    }
+    let kind = CheckedPath::from(path.to_owned())?.kind;
+    FILE_KINDS.lock().unwrap().insert(hash.to_owned(), kind);
+    Ok(kind)
+}
+
 #[derive(Debug)]
 struct AnyHowErrorAdapter {
     err: anyhow::Error,
+    status: StatusCode,
+}
+
+impl AnyHowErrorAdapter {
+    fn bad_request(err: anyhow::Error) -> Self {
+        Self {
+            err,
+            status: StatusCode::BAD_REQUEST,
+        }
+    }
 }
 
 impl std::fmt::Display for AnyHowErrorAdapter {
@@ -26,27 +79,53 @@ impl std::fmt::Display for AnyHowErrorAdapter {
     }
 }
 
-impl actix_web::error::ResponseError for AnyHowErrorAdapter {}
+impl actix_web::error::ResponseError for AnyHowErrorAdapter {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+}
+
 impl From<anyhow::Error> for AnyHowErrorAdapter {
     fn from(err: anyhow::Error) -> Self {
-        Self { err }
+        Self {
+            err,
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+        }
     }
 }
 
 impl From<std::io::Error> for AnyHowErrorAdapter {
     fn from(err: std::io::Error) -> Self {
-        Self { err: err.into() }
+        anyhow::Error::from(err).into()
     }
 }
 
+impl From<pdftocairo::Error> for AnyHowErrorAdapter {
+    fn from(err: pdftocairo::Error) -> Self {
+        anyhow::Error::from(err).into()
+    }
+}
+
+#[derive(Deserialize)]
+struct PdfMatchingsQuery {
+    /// Reports the pages of an overlay group as the first page of the group.
+    #[serde(default)]
+    collapse_overlays: bool,
+}
+
 #[get("/pdf-matchings/{hash}")]
 async fn pdf_matches_handler(
     web::Path(pdf_hash): web::Path<String>,
+    query: web::Query<PdfMatchingsQuery>,
     data: web::Data<AppState>,
 ) -> actix_web::Result<Json<Vec<PdfVideoMatching>>, AnyHowErrorAdapter> {
     let mut db = data.db_pool.db().await?;
 
-    let result = db.get_pdf_video_matchings(&pdf_hash).await?;
+    let mut result = db.get_pdf_video_matchings(&pdf_hash).await?;
+    if query.collapse_overlays {
+        let overlay_groups = db.get_pdf_overlay_groups(&pdf_hash).await?;
+        result = collapse_overlays(result, &overlay_groups);
+    }
 
     Ok(Json(result))
 }
@@ -59,10 +138,150 @@ async fn files_handler(
     let mut db = data.db_pool.db().await?;
     let path = db.get_path(&hash).await?;
 
-    if let Some(path) = path {
-        Ok(NamedFile::open(path)?)
-    } else {
-        Err(anyhow!("Hash not known"))?
+    let path = match path {
+        Some(path) => path,
+        None => Err(anyhow!("Hash not known"))?,
+    };
+
+    // Checked first, so that range requests for videos neither sniff nor probe them again.
+    if let Some(playable_path) = get_cached_playable_video(&path, &hash) {
+        return Ok(NamedFile::open(playable_path)?);
+    }
+
+    let kind = get_file_kind(&path, &hash).ok();
+    match kind {
+        Some(Kind::Video) => {}
+        Some(Kind::Presentation) => {
+            // Presentations are served as the pdf they have been converted to.
+            let pdf_path = web::block(move || convert_presentation(&path, &hash))
+                .await
+                .map_err(|err| match err {
+                    BlockingError::Error(err) => err,
+                    BlockingError::Canceled => anyhow!("Presentation conversion was canceled"),
+                })?;
+            return Ok(NamedFile::open(pdf_path)?);
+        }
+        Some(Kind::ImageDir) => Err(anyhow!("Image decks can only be served page by page"))?,
+        _ => return Ok(NamedFile::open(path)?),
+    }
+
+    // Videos that browsers cannot play are converted once and then served from the cache.
+    // Transcoding can take a long time, so the video is served as it is until the conversion is done.
+    // `/files/{hash}/conversion` tells when the converted video can be requested.
+    start_browser_conversion(path.clone(), hash);
+    Ok(NamedFile::open(path)?)
+}
+
+#[derive(Serialize)]
+struct ConversionStatus {
+    /// Whether the video is being converted for the browser.
+    /// Once done, `/files/{hash}` serves the converted video.
+    converting: bool,
+}
+
+#[get("/files/{hash}/conversion")]
+async fn conversion_handler(web::Path(hash): web::Path<String>) -> Json<ConversionStatus> {
+    Json(ConversionStatus {
+        converting: is_converting(&hash),
+    })
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    q: String,
+    limit: Option<u32>,
+}
+
+#[get("/search")]
+async fn search_handler(
+    query: web::Query<SearchQuery>,
+    data: web::Data<AppState>,
+) -> actix_web::Result<Json<Vec<PageSearchHit>>, AnyHowErrorAdapter> {
+    let mut db = data.db_pool.db().await?;
+
+    let result = db.search_pages(&query.q, query.limit.unwrap_or(50)).await?;
+
+    Ok(Json(result))
+}
+
+#[derive(Deserialize)]
+struct PageQuery {
+    /// The size of the longer side of the image in pixels.
+    /// It is rounded up to one of `PAGE_SIZE_BUCKETS`.
+    size: Option<u32>,
+}
+
+/// Serves an image of a deck page. `page_nr` is 1-based.
+#[get("/pages/{pdf_hash}/{page_nr}")]
+async fn page_handler(
+    web::Path((pdf_hash, page_nr)): web::Path<(String, u32)>,
+    query: web::Query<PageQuery>,
+    data: web::Data<AppState>,
+) -> actix_web::Result<NamedFile, AnyHowErrorAdapter> {
+    if page_nr == 0 {
+        return Err(AnyHowErrorAdapter::bad_request(anyhow!(
+            "Page numbers start with 1"
+        )));
+    }
+    let mut db = data.db_pool.db().await?;
+
+    if query.size.is_none() {
+        // Use the pages that have been extracted for matching.
+        if let Some(extracted) = db.get_pdf_extracted_pages_dir(&pdf_hash).await? {
+            if extracted.finished {
+                let page = read_pages(&extracted.dir)?
+                    .into_iter()
+                    .find(|p| p.page_nr == page_nr);
+                if let Some(page) = page {
+                    return Ok(NamedFile::open(page.image_path)?);
+                }
+            }
+        }
+    }
+
+    let deck_path = match db.get_path(&pdf_hash).await? {
+        Some(deck_path) => deck_path,
+        None => Err(anyhow!("Hash not known"))?,
+    };
+    let size = query.size.map_or(DEFAULT_PAGE_SIZE, round_page_size);
+    let image_path = web::block(move || {
+        let kind = get_file_kind(&deck_path, &pdf_hash)?;
+        render_page_preview(&deck_path, &pdf_hash, kind, page_nr, size)
+    })
+    .await
+    .map_err(|err| match err {
+        BlockingError::Error(err) => err,
+        BlockingError::Canceled => anyhow!("Page rendering was canceled"),
+    })?;
+    Ok(NamedFile::open(image_path)?)
+}
+
+#[get("/thumbnails/{video_hash}/sprite.jpg")]
+async fn sprite_handler(
+    web::Path(video_hash): web::Path<String>,
+) -> actix_web::Result<NamedFile, AnyHowErrorAdapter> {
+    Ok(NamedFile::open(
+        get_thumbnail_dir(&video_hash).join(SPRITE_FILE_NAME),
+    )?)
+}
+
+#[get("/thumbnails/{video_hash}/thumbnails.vtt")]
+async fn thumbnails_vtt_handler(
+    web::Path(video_hash): web::Path<String>,
+) -> actix_web::Result<NamedFile, AnyHowErrorAdapter> {
+    Ok(NamedFile::open(
+        get_thumbnail_dir(&video_hash).join(VTT_FILE_NAME),
+    )?)
+}
+
+/// Serves the thumbnail of the segment that is shown at the given offset of the video.
+#[get("/thumbnails/{video_hash}/{ms}")]
+async fn thumbnail_handler(
+    web::Path((video_hash, ms)): web::Path<(String, u64)>,
+) -> actix_web::Result<NamedFile, AnyHowErrorAdapter> {
+    match find_thumbnail(&video_hash, ms)? {
+        Some(path) => Ok(NamedFile::open(path)?),
+        None => Err(anyhow!("No thumbnail known"))?,
     }
 }
 
@@ -112,7 +331,13 @@ pub async fn start_server(pdf_hash: Option<String>) -> Result<()> {
                 db_pool: db_pool.clone(),
             })
             .service(files_handler)
+            .service(conversion_handler)
             .service(pdf_matches_handler)
+            .service(page_handler)
+            .service(search_handler)
+            .service(sprite_handler)
+            .service(thumbnails_vtt_handler)
+            .service(thumbnail_handler)
             .service(index)
             .service(dist)
     })
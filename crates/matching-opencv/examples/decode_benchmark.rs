@@ -0,0 +1,51 @@
+//! Compares how fast the frames of a video are sampled with sequential decoding and with seeking.
+//!
+//! ```sh
+//! cargo run --release --example decode_benchmark -- video.mp4 [interval secs]
+//! ```
+//!
+//! Seeking pays off for long intervals and videos with frequent keyframes.
+//! Both strategies should yield the same frames; the number of frames is printed to compare them.
+
+use matching_opencv::{Decoding, VideoCaptureIter};
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let video_path = PathBuf::from(
+        args.next()
+            .expect("Usage: decode_benchmark <video> [interval]"),
+    );
+    let interval = Duration::from_secs_f64(
+        args.next()
+            .map(|s| s.parse().expect("Invalid interval"))
+            .unwrap_or(5.0),
+    );
+
+    let mut video = VideoCaptureIter::open(&video_path, interval);
+    let video_secs = video.total_time().as_secs_f64();
+    println!(
+        "{:?}: {:.0}s, {} frames, sampled every {:?}",
+        video_path,
+        video_secs,
+        video.total_frames(),
+        interval
+    );
+
+    for &decoding in &[Decoding::Sequential, Decoding::Seek] {
+        let start = Instant::now();
+        let frames = VideoCaptureIter::open_with_decoding(&video_path, interval, decoding).count();
+        let elapsed = start.elapsed().as_secs_f64();
+        println!(
+            "{:?}: {} frames in {:.2}s ({:.1} sampled frames/s, {:.1}x real time)",
+            decoding,
+            frames,
+            elapsed,
+            frames as f64 / elapsed,
+            video_secs / elapsed
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! Searches for matching parameters that classify a labeled fixture set best.
+//!
+//! Every directory of the fixture set contains slides `<n>-slide.png` and video frames `<n>-frame.png`.
+//! A frame is expected to show the slide with the same `<n>`, or no slide at all if there is no such slide.
+//!
+//! ```sh
+//! cargo run --release --example tune_params -- [fixture dir] [--output params.toml]
+//! ```
+//!
+//! The resulting parameter file can be passed to `slideo --matching-params`.
+//! `frame_change_threshold` only affects videos and is not tuned.
+
+use matching::{MatchableImage, ProgressReporter};
+use matching_opencv::{MatchingParams, OpenCVImageVideoMatcher};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Slide<'a> {
+    path: &'a Path,
+}
+
+impl<'a> MatchableImage for Slide<'a> {
+    fn get_path(&self) -> &Path {
+        self.path
+    }
+}
+
+struct Fixture {
+    slides: Vec<PathBuf>,
+    frames: Vec<PathBuf>,
+    /// The index of the expected slide for every frame.
+    expected: Vec<Option<usize>>,
+}
+
+fn read_fixture(dir: &Path) -> Fixture {
+    let mut slides = HashMap::new();
+    let mut frames = HashMap::new();
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+        if let Some(key) = name.strip_suffix("-slide") {
+            slides.insert(key.to_owned(), path);
+        } else if let Some(key) = name.strip_suffix("-frame") {
+            frames.insert(key.to_owned(), path);
+        }
+    }
+
+    let mut slides: Vec<_> = slides.into_iter().collect();
+    slides.sort();
+    let mut frames: Vec<_> = frames.into_iter().collect();
+    frames.sort();
+    let expected = frames
+        .iter()
+        .map(|(key, _)| slides.iter().position(|(k, _)| k == key))
+        .collect();
+
+    Fixture {
+        slides: slides.into_iter().map(|(_, p)| p).collect(),
+        frames: frames.into_iter().map(|(_, p)| p).collect(),
+        expected,
+    }
+}
+
+/// Returns the number of correctly classified frames.
+fn evaluate(fixtures: &[Fixture], params: &MatchingParams) -> usize {
+    let matcher = OpenCVImageVideoMatcher::default().with_params(params.clone());
+    fixtures
+        .iter()
+        .map(|fixture| {
+            let slides = fixture
+                .slides
+                .iter()
+                .map(|path| Slide { path })
+                .collect::<Vec<_>>();
+            let actual = matcher.match_frames(
+                slides.clone(),
+                &fixture.frames,
+                ProgressReporter::new(Arc::new(|_, _, _| {})),
+            );
+            actual
+                .iter()
+                .zip(&fixture.expected)
+                .filter(|(actual, expected)| {
+                    actual.map(|s| s.path) == expected.map(|idx| slides[idx].path)
+                })
+                .count()
+        })
+        .sum()
+}
+
+type Setter = fn(&mut MatchingParams, f64);
+
+fn search_space() -> Vec<(&'static str, Vec<f64>, Setter)> {
+    vec![
+        ("knn_neighbors", vec![10.0, 20.0, 30.0, 50.0], |p, v| {
+            p.knn_neighbors = v as i32
+        }),
+        (
+            "distance_tolerance",
+            vec![1.0, 1.02, 1.05, 1.1, 1.2],
+            |p, v| p.distance_tolerance = v,
+        ),
+        ("max_candidates", vec![10.0, 20.0, 40.0, 80.0], |p, v| {
+            p.max_candidates = v as usize
+        }),
+        ("max_rated_candidates", vec![5.0, 10.0, 20.0], |p, v| {
+            p.max_rated_candidates = v as usize
+        }),
+        ("min_inliers", vec![10.0, 20.0, 30.0, 50.0, 80.0], |p, v| {
+            p.min_inliers = v
+        }),
+        ("min_relative_inliers", vec![0.1, 0.2, 0.3, 0.5], |p, v| {
+            p.min_relative_inliers = v
+        }),
+        ("min_similarity", vec![0.3, 0.4, 0.5, 0.6, 0.7], |p, v| {
+            p.min_similarity = v
+        }),
+    ]
+}
+
+fn main() {
+    let mut fixture_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../data/matchings");
+    let mut output = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--output" => output = Some(PathBuf::from(args.next().expect("Missing output path"))),
+            _ => fixture_dir = PathBuf::from(arg),
+        }
+    }
+
+    let mut fixtures = Vec::new();
+    for entry in fs::read_dir(&fixture_dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            fixtures.push(read_fixture(&path));
+        }
+    }
+    let total: usize = fixtures.iter().map(|f| f.frames.len()).sum();
+
+    let mut params = MatchingParams::default();
+    let mut best = evaluate(&fixtures, &params);
+    println!("Defaults: {}/{} frames correct", best, total);
+
+    // Coordinate descent: optimize one parameter at a time until nothing improves.
+    // Ties keep the current value, so parameters only move away from the defaults if it helps.
+    for round in 1..=5 {
+        let mut improved = false;
+        for (name, values, set) in search_space() {
+            for &value in &values {
+                let mut candidate = params.clone();
+                set(&mut candidate, value);
+                if candidate == params {
+                    continue;
+                }
+                let score = evaluate(&fixtures, &candidate);
+                if score > best {
+                    println!(
+                        "Round {}: {} = {} gives {}/{} frames correct",
+                        round, name, value, score, total
+                    );
+                    best = score;
+                    params = candidate;
+                    improved = true;
+                }
+            }
+        }
+        if !improved || best == total {
+            break;
+        }
+    }
+
+    let toml = toml::to_string(&params).unwrap();
+    match output {
+        Some(path) => {
+            fs::write(&path, toml).unwrap();
+            println!("Wrote {:?}", path);
+        }
+        None => print!("\n{}", toml),
+    }
+}
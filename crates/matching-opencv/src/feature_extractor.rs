@@ -1,42 +1,189 @@
 use opencv::{
-    core::{no_array, KeyPoint, Mat, Ptr, Vector},
-    features2d::{self, ORB_ScoreType},
-    prelude::{Feature2DTrait, ORB},
+    core::{no_array, KeyPoint, Mat, Ptr, ToInputArray, Vector},
+    features2d::{AKAZE_DescriptorType, KAZE_DiffusivityType, ORB_ScoreType},
+    prelude::{Feature2DTrait, AKAZE, BRISK, ORB, SIFT},
 };
 
+/// Which keypoint detector and descriptor is used, with its parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeatureExtractorConfig {
+    Orb {
+        features: i32,
+        scale_factor: f32,
+        levels: i32,
+        edge_threshold: i32,
+        patch_size: i32,
+        fast_threshold: i32,
+    },
+    Akaze {
+        /// The detector response threshold. Lower values find more keypoints.
+        threshold: f32,
+        octaves: i32,
+    },
+    Brisk {
+        /// The FAST/AGAST detection threshold. Lower values find more keypoints.
+        threshold: i32,
+        octaves: i32,
+    },
+    /// Slower, but more robust to blur and scale, e.g. for low-resolution recordings.
+    Sift {
+        /// The number of best features to keep. 0 keeps all.
+        features: i32,
+        contrast_threshold: f64,
+        edge_threshold: f64,
+    },
+}
+
+impl Default for FeatureExtractorConfig {
+    fn default() -> Self {
+        FeatureExtractorConfig::orb()
+    }
+}
+
+impl FeatureExtractorConfig {
+    pub fn orb() -> Self {
+        FeatureExtractorConfig::Orb {
+            features: 2000,
+            scale_factor: 1.2,
+            levels: 8,
+            edge_threshold: 62,
+            patch_size: 62,
+            fast_threshold: 20,
+        }
+    }
+
+    pub fn akaze() -> Self {
+        FeatureExtractorConfig::Akaze {
+            threshold: 0.001,
+            octaves: 4,
+        }
+    }
+
+    pub fn brisk() -> Self {
+        FeatureExtractorConfig::Brisk {
+            threshold: 30,
+            octaves: 3,
+        }
+    }
+
+    pub fn sift() -> Self {
+        FeatureExtractorConfig::Sift {
+            features: 2000,
+            contrast_threshold: 0.04,
+            edge_threshold: 10.0,
+        }
+    }
+
+    pub fn descriptor_kind(&self) -> DescriptorKind {
+        match self {
+            FeatureExtractorConfig::Sift { .. } => DescriptorKind::Float,
+            _ => DescriptorKind::Binary,
+        }
+    }
+}
+
+/// Binary descriptors are compared by hamming distance, float descriptors by euclidean distance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DescriptorKind {
+    Binary,
+    Float,
+}
+
+enum Detector {
+    Orb(Ptr<dyn ORB>),
+    Akaze(Ptr<dyn AKAZE>),
+    Brisk(Ptr<dyn BRISK>),
+    Sift(Ptr<dyn SIFT>),
+}
+
 pub struct FeatureExtractor {
-    ptr: Ptr<dyn features2d::ORB>,
+    detector: Detector,
 }
 
+unsafe impl Send for FeatureExtractor {}
+
 impl FeatureExtractor {
-    pub fn default() -> FeatureExtractor {
-        let orb = <dyn ORB>::create(
-            /* nfeatures: */ 2000,
-            /* scale_factor: */ 1.2,
-            /* nlevels: */ 8,
-            /* edge_threshold: */ 62,
-            /* first_level: */ 0,
-            /* wta_k: */ 2,
-            /* score_type: */ ORB_ScoreType::FAST_SCORE,
-            /* patch_size: */ 62,
-            /* fast_threshold: */ 20,
-        )
-        .unwrap();
-
-        FeatureExtractor { ptr: orb }
-    }
-
-    pub fn find_keypoints_and_descriptors(&mut self, src: &Mat) -> KeypointsWithDescriptors {
+    pub fn new(config: &FeatureExtractorConfig) -> FeatureExtractor {
+        let detector = match *config {
+            FeatureExtractorConfig::Orb {
+                features,
+                scale_factor,
+                levels,
+                edge_threshold,
+                patch_size,
+                fast_threshold,
+            } => Detector::Orb(
+                <dyn ORB>::create(
+                    features,
+                    scale_factor,
+                    levels,
+                    edge_threshold,
+                    /* first_level: */ 0,
+                    /* wta_k: */ 2,
+                    /* score_type: */ ORB_ScoreType::FAST_SCORE,
+                    patch_size,
+                    fast_threshold,
+                )
+                .unwrap(),
+            ),
+            FeatureExtractorConfig::Akaze { threshold, octaves } => Detector::Akaze(
+                <dyn AKAZE>::create(
+                    AKAZE_DescriptorType::DESCRIPTOR_MLDB,
+                    /* descriptor_size: */ 0,
+                    /* descriptor_channels: */ 3,
+                    threshold,
+                    octaves,
+                    /* octave_layers: */ 4,
+                    KAZE_DiffusivityType::DIFF_PM_G2,
+                )
+                .unwrap(),
+            ),
+            FeatureExtractorConfig::Brisk { threshold, octaves } => Detector::Brisk(
+                <dyn BRISK>::create(threshold, octaves, /* pattern_scale: */ 1.0).unwrap(),
+            ),
+            FeatureExtractorConfig::Sift {
+                features,
+                contrast_threshold,
+                edge_threshold,
+            } => Detector::Sift(
+                <dyn SIFT>::create(
+                    features,
+                    /* octave_layers: */ 3,
+                    contrast_threshold,
+                    edge_threshold,
+                    /* sigma: */ 1.6,
+                )
+                .unwrap(),
+            ),
+        };
+
+        FeatureExtractor { detector }
+    }
+
+    fn feature2d(&mut self) -> &mut dyn Feature2DTrait {
+        match &mut self.detector {
+            Detector::Orb(d) => d,
+            Detector::Akaze(d) => d,
+            Detector::Brisk(d) => d,
+            Detector::Sift(d) => d,
+        }
+    }
+
+    /// Only detects keypoints where `mask` is non-zero, if given.
+    pub fn find_keypoints_and_descriptors(
+        &mut self,
+        src: &Mat,
+        mask: Option<&Mat>,
+    ) -> KeypointsWithDescriptors {
+        let no_mask = no_array().unwrap();
+        let mask: &dyn ToInputArray = match mask {
+            Some(mask) => mask,
+            None => &no_mask,
+        };
         let mut descriptors = Mat::default();
         let mut keypoints = Vector::new();
-        self.ptr
-            .detect_and_compute(
-                src,
-                &no_array().unwrap(),
-                &mut keypoints,
-                &mut descriptors,
-                false,
-            )
+        self.feature2d()
+            .detect_and_compute(src, mask, &mut keypoints, &mut descriptors, false)
             .unwrap();
 
         KeypointsWithDescriptors {
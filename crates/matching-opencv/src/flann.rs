@@ -1,7 +1,8 @@
+use crate::feature_extractor::DescriptorKind;
 use opencv::features2d::FlannBasedMatcher;
 use opencv::{
     core::{no_array, DMatch, Ptr, Vector},
-    flann::{IndexParams, SearchParams, FLANN_INDEX_LSH},
+    flann::{IndexParams, SearchParams, FLANN_INDEX_KDTREE, FLANN_INDEX_LSH},
     prelude::*,
     types::VectorOfMat,
 };
@@ -11,12 +12,20 @@ struct BaseFlannMatcher {
 }
 
 impl BaseFlannMatcher {
-    pub fn default() -> Self {
+    pub fn new(descriptor_kind: DescriptorKind) -> Self {
         let mut index_params = Ptr::new(IndexParams::default().unwrap());
-        index_params.set_int(&"table_number", 6).unwrap();
-        index_params.set_int(&"key_size", 12).unwrap();
-        index_params.set_int(&"multi_probe_level", 1).unwrap();
-        index_params.set_algorithm(FLANN_INDEX_LSH).unwrap();
+        match descriptor_kind {
+            DescriptorKind::Binary => {
+                index_params.set_int(&"table_number", 6).unwrap();
+                index_params.set_int(&"key_size", 12).unwrap();
+                index_params.set_int(&"multi_probe_level", 1).unwrap();
+                index_params.set_algorithm(FLANN_INDEX_LSH).unwrap();
+            }
+            DescriptorKind::Float => {
+                index_params.set_int(&"trees", 5).unwrap();
+                index_params.set_algorithm(FLANN_INDEX_KDTREE).unwrap();
+            }
+        }
 
         let search_params = Ptr::new(SearchParams::new(32, 0.0, true, false).unwrap());
 
@@ -62,8 +71,8 @@ pub struct FlannMatcher {
 }
 
 impl FlannMatcher {
-    pub fn new(descriptors: impl Iterator<Item = Mat>) -> Self {
-        let mut matcher = BaseFlannMatcher::default();
+    pub fn new(descriptor_kind: DescriptorKind, descriptors: impl Iterator<Item = Mat>) -> Self {
+        let mut matcher = BaseFlannMatcher::new(descriptor_kind);
         matcher.add_descriptors(descriptors);
         matcher.train();
 
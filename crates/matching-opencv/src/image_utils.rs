@@ -1,7 +1,14 @@
 use opencv::{
-    calib3d::{estimate_affine_partial_2d, RANSAC},
-    core::{no_array, norm2, Point2f, Size, Vector, NORM_L2},
-    imgproc::{resize, INTER_AREA},
+    calib3d::{estimate_affine_partial_2d, find_homography, RANSAC},
+    core::{
+        count_non_zero, no_array, norm2, Point2f, Scalar, Size, ToInputArray, Vector,
+        BORDER_CONSTANT, CV_64F, NORM_L2,
+    },
+    imgcodecs::imencode,
+    imgproc::{
+        resize, threshold, warp_affine, warp_perspective, INTER_AREA, THRESH_BINARY,
+        WARP_INVERSE_MAP,
+    },
     prelude::*,
 };
 
@@ -19,20 +26,103 @@ pub fn to_small_image(mat: &Mat) -> Mat {
     scaled_mat
 }
 
-pub fn compute_similarity(img1: &Mat, img2: &Mat) -> f32 {
-    let error_l2 = norm2(img1, img2, NORM_L2, &no_array().unwrap()).unwrap();
-    let p = img1.rows() * img1.cols();
+/// Scales a mask to the size of `to_small_image`. Pixels that are partially masked are masked.
+pub fn to_small_mask(mask: &Mat) -> Mat {
+    let mut small_mask = Mat::default();
+    threshold(
+        &to_small_image(mask),
+        &mut small_mask,
+        254.0,
+        255.0,
+        THRESH_BINARY,
+    )
+    .unwrap();
+    small_mask
+}
+
+pub const THUMBNAIL_WIDTH: i32 = 160;
+
+/// Scales the image to the thumbnail width and encodes it as JPEG.
+pub fn to_thumbnail_jpeg(mat: &Mat) -> Vec<u8> {
+    let size = mat.size().unwrap();
+    let height = (size.height as f32 * THUMBNAIL_WIDTH as f32 / size.width as f32).round() as i32;
+    let mut thumbnail = Mat::default();
+    resize(
+        &mat,
+        &mut thumbnail,
+        Size::new(THUMBNAIL_WIDTH, height),
+        0.0,
+        0.0,
+        INTER_AREA,
+    )
+    .unwrap();
+
+    let mut buf = Vector::<u8>::new();
+    imencode(".jpg", &thumbnail, &mut buf, &Vector::new()).unwrap();
+    buf.to_vec()
+}
+
+/// Compares two color images of the same size, from 0 (inverted) to 1 (identical).
+/// If a mask is given, only the pixels where it is non-zero are compared.
+pub fn compute_similarity(img1: &Mat, img2: &Mat, mask: Option<&Mat>) -> f32 {
+    let no_mask = no_array().unwrap();
+    let (mask, p): (&dyn ToInputArray, i32) = match mask {
+        Some(mask) => (mask, count_non_zero(mask).unwrap()),
+        None => (&no_mask, img1.rows() * img1.cols()),
+    };
+    if p == 0 {
+        return 0.0;
+    }
+    let error_l2 = norm2(img1, img2, NORM_L2, mask).unwrap();
     let max_error = ((255.0 * 255.0 * 3.0) * (p as f32)).sqrt();
     return 1.0 - (error_l2 as f32) / max_error;
 }
 
+/// How slides are mapped onto video frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransformationModel {
+    /// Rotation, uniform scale and translation. Suited for screen recordings.
+    #[default]
+    Similarity,
+    /// A full perspective transformation, e.g. for a camera filming a projector screen at an angle.
+    Homography,
+}
+
 pub struct Transformation2D {
     pub mat: Mat,
+    pub model: TransformationModel,
 }
 
 impl Transformation2D {
-    pub fn new(mat: Mat) -> Transformation2D {
-        Transformation2D { mat }
+    pub fn new(mat: Mat, model: TransformationModel) -> Transformation2D {
+        Transformation2D { mat, model }
+    }
+
+    /// Maps the image back onto the slide, i.e. applies the inverse transformation.
+    pub fn warp_inverse(&self, img: &Mat, slide_size: Size) -> Mat {
+        let mut result = Mat::default();
+        match self.model {
+            TransformationModel::Similarity => warp_affine(
+                img,
+                &mut result,
+                &self.mat,
+                slide_size,
+                WARP_INVERSE_MAP,
+                BORDER_CONSTANT,
+                Scalar::new(0.0, 0.0, 0.0, 0.0),
+            ),
+            TransformationModel::Homography => warp_perspective(
+                img,
+                &mut result,
+                &self.mat,
+                slide_size,
+                WARP_INVERSE_MAP,
+                BORDER_CONSTANT,
+                Scalar::new(0.0, 0.0, 0.0, 0.0),
+            ),
+        }
+        .unwrap();
+        result
     }
 }
 
@@ -42,6 +132,16 @@ pub struct TransformationWithInliers {
 }
 
 impl Transformation2D {
+    pub fn estimate<I>(model: TransformationModel, points: I) -> TransformationWithInliers
+    where
+        I: Iterator<Item = (Point2f, Point2f)>,
+    {
+        match model {
+            TransformationModel::Similarity => Transformation2D::estimate_affine(points),
+            TransformationModel::Homography => Transformation2D::estimate_homography(points),
+        }
+    }
+
     pub fn estimate_affine<I>(points: I) -> TransformationWithInliers
     where
         I: Iterator<Item = (Point2f, Point2f)>,
@@ -54,7 +154,37 @@ impl Transformation2D {
         assert!(from.len() == inliers.len());
 
         TransformationWithInliers {
-            transformation: Transformation2D::new(mat),
+            transformation: Transformation2D::new(mat, TransformationModel::Similarity),
+            inlier_flags: inliers.iter().map(|v| v == 1).collect(),
+        }
+    }
+
+    pub fn estimate_homography<I>(points: I) -> TransformationWithInliers
+    where
+        I: Iterator<Item = (Point2f, Point2f)>,
+    {
+        let (from, to): (Vector<Point2f>, Vector<Point2f>) = points.unzip();
+
+        let mut inliers = Vector::<u8>::default();
+        // A homography needs at least 4 point pairs. If it cannot be estimated, all points are outliers.
+        let mat = if from.len() >= 4 {
+            find_homography(&from, &to, &mut inliers, RANSAC, 3.0).unwrap()
+        } else {
+            Mat::default()
+        };
+        if mat.empty().unwrap() {
+            return TransformationWithInliers {
+                transformation: Transformation2D::new(
+                    Mat::eye(3, 3, CV_64F).unwrap().to_mat().unwrap(),
+                    TransformationModel::Homography,
+                ),
+                inlier_flags: vec![false; from.len()],
+            };
+        }
+        assert!(from.len() == inliers.len());
+
+        TransformationWithInliers {
+            transformation: Transformation2D::new(mat, TransformationModel::Homography),
             inlier_flags: inliers.iter().map(|v| v == 1).collect(),
         }
     }
@@ -1,25 +1,34 @@
 mod feature_extractor;
 mod flann;
 mod image_utils;
+mod overlays;
+mod params;
+mod preprocessing;
+mod sprite;
 mod video_capture;
 
 use self::{
     flann::FlannMatcher,
-    image_utils::{compute_similarity, to_small_image, Transformation2D},
+    image_utils::{
+        compute_similarity, to_small_image, to_small_mask, to_thumbnail_jpeg, Transformation2D,
+    },
 };
 use feature_extractor::FeatureExtractor;
 use matching::{
-    ImageVideoMatcher, MatchableImage, Matching, ProgressReporter, VideoMatcher, VideoMatcherTask,
+    next_pages, smooth_matchings, FrameCandidates, ImageVideoMatcher, MatchableImage, Matching,
+    ProgressReporter, SmoothingParams, VideoMatcher, VideoMatcherTask,
 };
 use opencv::{
-    core::{KeyPoint, Scalar},
+    core::KeyPoint,
     //highgui::{imshow, wait_key},
     imgcodecs::*,
-    imgproc::{cvt_color, warp_affine, COLOR_BGRA2BGR, WARP_INVERSE_MAP},
+    imgproc::{cvt_color, COLOR_BGRA2BGR},
     prelude::*,
 };
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use std::sync::Arc;
+use overlays::{detect_overlay_groups, OverlayGroup};
+use rayon::iter::{IntoParallelIterator, ParallelBridge, ParallelIterator};
+use std::ops::Range;
+use std::sync::{mpsc::sync_channel, Arc};
 use std::time::Duration;
 use std::{cell::RefCell, sync::Mutex};
 use std::{collections::HashMap, path::PathBuf};
@@ -28,12 +37,124 @@ use std::{
     sync::atomic::{AtomicUsize, Ordering},
 };
 use thread_local::ThreadLocal;
-use video_capture::{MarkSimilarIter, VideoCaptureIter};
+use video_capture::{split_time_ranges, MarkSimilarIter, SceneChangeIter, VideoCaptureIter};
+
+pub use feature_extractor::FeatureExtractorConfig;
+pub use image_utils::TransformationModel;
+pub use params::MatchingParams;
+pub use preprocessing::{Preprocessing, PreprocessingStep, RelativeRect};
+pub use sprite::{create_sprite_sheet, SpriteSheet};
+pub use video_capture::{Decoding, Sampling, VideoCaptureIter};
+
+/// Returns the width and height of the frames of the given video in pixels.
+/// Returns `None` if the video cannot be read.
+pub fn get_video_frame_size(video_path: &Path) -> Option<(u32, u32)> {
+    let vid = VideoCaptureIter::open(video_path, Duration::from_secs(1));
+    match vid.frame_size() {
+        (0, _) | (_, 0) => None,
+        size => Some(size),
+    }
+}
 
-#[derive(Default)]
-pub struct OpenCVImageVideoMatcher {}
+pub struct OpenCVImageVideoMatcher {
+    /// Only every frame at this interval is matched.
+    interval: Duration,
+    sampling: Sampling,
+    decoding: Decoding,
+    /// If set, the matched images are smoothed over time.
+    smoothing: Option<SmoothingParams>,
+    /// Applied to video frames before their features are extracted.
+    preprocessing: Preprocessing,
+    transformation_model: TransformationModel,
+    feature_extractor: FeatureExtractorConfig,
+    params: MatchingParams,
+}
+
+impl Default for OpenCVImageVideoMatcher {
+    fn default() -> Self {
+        OpenCVImageVideoMatcher::new(Duration::from_secs(5))
+    }
+}
 
 impl OpenCVImageVideoMatcher {
+    pub fn new(interval: Duration) -> Self {
+        OpenCVImageVideoMatcher {
+            interval,
+            sampling: Sampling::default(),
+            decoding: Decoding::default(),
+            smoothing: Some(SmoothingParams::default()),
+            preprocessing: Preprocessing::default(),
+            transformation_model: TransformationModel::default(),
+            feature_extractor: FeatureExtractorConfig::default(),
+            params: MatchingParams::default(),
+        }
+    }
+
+    /// Sets which frames of the video are matched.
+    pub fn with_sampling(self, sampling: Sampling) -> Self {
+        OpenCVImageVideoMatcher { sampling, ..self }
+    }
+
+    /// Sets how the frames between sampled frames are skipped.
+    pub fn with_decoding(self, decoding: Decoding) -> Self {
+        OpenCVImageVideoMatcher { decoding, ..self }
+    }
+
+    /// Sets how matchings are smoothed over time. `None` matches every frame on its own.
+    pub fn with_smoothing(self, smoothing: Option<SmoothingParams>) -> Self {
+        OpenCVImageVideoMatcher { smoothing, ..self }
+    }
+
+    pub fn with_preprocessing(self, preprocessing: Preprocessing) -> Self {
+        OpenCVImageVideoMatcher {
+            preprocessing,
+            ..self
+        }
+    }
+
+    /// Sets how slides are mapped onto video frames.
+    pub fn with_transformation_model(self, transformation_model: TransformationModel) -> Self {
+        OpenCVImageVideoMatcher {
+            transformation_model,
+            ..self
+        }
+    }
+
+    /// Sets the keypoint detector that is used for both the images and the video frames.
+    pub fn with_feature_extractor(self, feature_extractor: FeatureExtractorConfig) -> Self {
+        OpenCVImageVideoMatcher {
+            feature_extractor,
+            ..self
+        }
+    }
+
+    /// Sets the thresholds that decide which image a frame shows.
+    pub fn with_params(self, params: MatchingParams) -> Self {
+        OpenCVImageVideoMatcher { params, ..self }
+    }
+
+    /// Matches the given frame images (e.g. screenshots of a video) with the images.
+    /// The frames are matched independently of each other, so no smoothing is applied.
+    pub fn match_frames<I: MatchableImage + Send + Sync + Copy + Eq>(
+        &self,
+        images: Vec<I>,
+        frames: &[PathBuf],
+        progress_reporter: ProgressReporter,
+    ) -> Vec<Option<I>> {
+        let matcher = self.create_video_matcher(images, progress_reporter);
+        frames
+            .iter()
+            .map(|path| {
+                let frame = imread(&path.to_string_lossy(), IMREAD_COLOR).unwrap();
+                if frame.empty().unwrap() {
+                    panic!("Could not read file '{:?}'", path);
+                }
+                let (matching, _) = matcher.match_images_with_frame(frame, Duration::default(), 0);
+                matching.image
+            })
+            .collect()
+    }
+
     fn create_video_matcher<'i, I: MatchableImage + Send + Sync + Copy + Eq + 'i>(
         &self,
         images: Vec<I>,
@@ -42,9 +163,15 @@ impl OpenCVImageVideoMatcher {
         let len = images.len() as u64;
         progress_reporter.report(0, len, "Analyzing PDF pages...");
         let processed_pages = AtomicUsize::new(0);
+        let feature_extractors = Arc::new(ThreadLocal::new());
         let processed_images: Vec<ProcessedImage<I>> = images
             .into_par_iter()
-            .map(ProcessedImage::compute)
+            .enumerate()
+            .map(|(idx, img)| {
+                let extractor = feature_extractors
+                    .get_or(|| RefCell::new(FeatureExtractor::new(&self.feature_extractor)));
+                ProcessedImage::compute(idx, img, &mut extractor.borrow_mut())
+            })
             .map(|v| {
                 progress_reporter.report(
                     (processed_pages.fetch_add(1, Ordering::Relaxed) + 1) as u64,
@@ -55,11 +182,23 @@ impl OpenCVImageVideoMatcher {
             })
             .collect();
 
+        let overlay_groups = detect_deck_overlay_groups(&processed_images);
+
         progress_reporter.report(len, len, "PDF page analysis successful.");
 
         OpenCVVideoMatcher {
             shared_flanns: Arc::new(ThreadLocal::new()),
+            feature_extractors,
             images: Arc::new(processed_images),
+            overlay_groups: Arc::new(overlay_groups),
+            interval: self.interval,
+            sampling: self.sampling,
+            decoding: self.decoding,
+            smoothing: self.smoothing.clone(),
+            preprocessing: self.preprocessing.clone(),
+            transformation_model: self.transformation_model,
+            feature_extractor: self.feature_extractor.clone(),
+            params: self.params.clone(),
         }
     }
 }
@@ -74,7 +213,37 @@ impl<'i> ImageVideoMatcher<'i> for OpenCVImageVideoMatcher {
     }
 }
 
+/// Detects overlay groups among runs of adjacent pages of the same deck.
+/// Pages of different decks or pages that are not adjacent (e.g. due to a page selection) are never grouped.
+fn detect_deck_overlay_groups<I: MatchableImage>(
+    images: &[ProcessedImage<I>],
+) -> Vec<OverlayGroup> {
+    let mut pages: Vec<(&str, usize, usize)> = images
+        .iter()
+        .filter_map(|img| {
+            img.source_img
+                .deck_position()
+                .map(|(deck, page_nr)| (deck, page_nr, img.idx))
+        })
+        .collect();
+    pages.sort_unstable();
+
+    let mut groups = Vec::new();
+    for run in pages.chunk_by(|a, b| a.0 == b.0 && a.1 + 1 == b.1) {
+        let imgs: Vec<&Mat> = run.iter().map(|p| &images[p.2].small_img).collect();
+        for mut group in detect_overlay_groups(&imgs) {
+            for member in &mut group.members {
+                *member = run[*member].2;
+            }
+            groups.push(group);
+        }
+    }
+    groups
+}
+
 struct ProcessedImage<I> {
+    /// The index of the image in the list of matched images.
+    pub idx: usize,
     pub source_img: I,
     pub keypoints: Vec<KeyPoint>,
     pub descriptors: Mat,
@@ -85,12 +254,8 @@ struct ProcessedImage<I> {
 unsafe impl<I: Send> Send for ProcessedImage<I> {}
 unsafe impl<I: Send> Sync for ProcessedImage<I> {}
 
-thread_local! {
-    static FEATURE_EXTRACTOR: RefCell<FeatureExtractor> = RefCell::new(FeatureExtractor::default());
-}
-
 impl<I: MatchableImage> ProcessedImage<I> {
-    pub fn compute(img: I) -> ProcessedImage<I> {
+    pub fn compute(idx: usize, img: I, extractor: &mut FeatureExtractor) -> ProcessedImage<I> {
         let path = img.get_path();
         if !path.exists() {
             panic!("File '{:?}' must exist", path);
@@ -103,10 +268,7 @@ impl<I: MatchableImage> ProcessedImage<I> {
         let mut normalized_img_mat = Mat::default();
         cvt_color(&img_mat, &mut normalized_img_mat, COLOR_BGRA2BGR, 0).unwrap();
 
-        let result = FEATURE_EXTRACTOR.with(|e| {
-            e.borrow_mut()
-                .find_keypoints_and_descriptors(&normalized_img_mat)
-        });
+        let result = extractor.find_keypoints_and_descriptors(&normalized_img_mat, None);
 
         /*
         let mut debug_keypoints = Mat::default().unwrap();
@@ -122,6 +284,7 @@ impl<I: MatchableImage> ProcessedImage<I> {
         wait_key(0).unwrap();*/
 
         ProcessedImage {
+            idx,
             source_img: img,
             descriptors: result.descriptors,
             keypoints: result.keypoints.iter().collect(),
@@ -133,7 +296,36 @@ impl<I: MatchableImage> ProcessedImage<I> {
 
 struct OpenCVVideoMatcher<I: Send> {
     images: Arc<Vec<ProcessedImage<I>>>,
+    overlay_groups: Arc<Vec<OverlayGroup>>,
     shared_flanns: Arc<ThreadLocal<RefCell<FlannMatcher>>>,
+    feature_extractors: Arc<ThreadLocal<RefCell<FeatureExtractor>>>,
+    interval: Duration,
+    sampling: Sampling,
+    decoding: Decoding,
+    smoothing: Option<SmoothingParams>,
+    preprocessing: Preprocessing,
+    transformation_model: TransformationModel,
+    feature_extractor: FeatureExtractorConfig,
+    params: MatchingParams,
+}
+
+impl<I: Send> Clone for OpenCVVideoMatcher<I> {
+    fn clone(&self) -> Self {
+        OpenCVVideoMatcher {
+            images: self.images.clone(),
+            overlay_groups: self.overlay_groups.clone(),
+            shared_flanns: self.shared_flanns.clone(),
+            feature_extractors: self.feature_extractors.clone(),
+            interval: self.interval,
+            sampling: self.sampling,
+            decoding: self.decoding,
+            smoothing: self.smoothing.clone(),
+            preprocessing: self.preprocessing.clone(),
+            transformation_model: self.transformation_model,
+            feature_extractor: self.feature_extractor.clone(),
+            params: self.params.clone(),
+        }
+    }
 }
 
 impl<'i, I: MatchableImage + Send + Copy + Eq + 'i> VideoMatcher<'i, I> for OpenCVVideoMatcher<I> {
@@ -142,82 +334,101 @@ impl<'i, I: MatchableImage + Send + Copy + Eq + 'i> VideoMatcher<'i, I> for Open
         video_path: &Path,
         progress_reporter: ProgressReporter,
     ) -> Box<dyn VideoMatcherTask<I> + 'i> {
-        let interval = Duration::from_secs(5);
-        let vid = VideoCaptureIter::open(&video_path, interval);
+        let interval = self.capture_interval();
+        let mut vid = VideoCaptureIter::open(&video_path, interval);
         let total_time = vid.total_time();
         let frames_to_process = (total_time.as_secs_f64() / interval.as_secs_f64()) as u64;
 
         progress_reporter.report(0, frames_to_process, "");
 
         Box::new(OpenCVVideoMatcherTask {
-            images: self.images.clone(),
-            shared_flanns: self.shared_flanns.clone(),
+            matcher: self.clone(),
             video_path: video_path.to_owned(),
             progress_reporter,
         })
     }
+
+    fn overlay_groups(&self) -> Vec<Vec<I>> {
+        self.overlay_groups
+            .iter()
+            .map(|g| {
+                g.members
+                    .iter()
+                    .map(|&idx| self.images[idx].source_img)
+                    .collect()
+            })
+            .collect()
+    }
 }
 
 struct OpenCVVideoMatcherTask<I: Send> {
-    images: Arc<Vec<ProcessedImage<I>>>,
-    shared_flanns: Arc<ThreadLocal<RefCell<FlannMatcher>>>,
+    matcher: OpenCVVideoMatcher<I>,
     video_path: PathBuf,
     progress_reporter: ProgressReporter,
 }
 
 impl<I: MatchableImage + Send + Copy + Eq> VideoMatcherTask<I> for OpenCVVideoMatcherTask<I> {
     fn process(&self) -> Vec<Matching<I>> {
-        let results = Arc::new(Mutex::new(Vec::<Matching<I>>::new()));
-
-        let mut frames_to_process = 0;
-
-        rayon::scope_fifo(|s| {
-            let interval = Duration::from_secs(5);
-            let vid = VideoCaptureIter::open(&self.video_path, interval);
-            let total_time = vid.total_time();
-            let total_frames = vid.total_frames();
-            frames_to_process = (total_time.as_secs_f64() / interval.as_secs_f64()) as u32;
-            let video_frames = MarkSimilarIter::new(vid);
-
-            // Add a matching to indicate the last frame.
-            let results = results.clone();
-            let mut list = results.lock().unwrap();
-            list.push(Matching {
-                image: None,
-                video_frame_idx: total_frames as usize,
-                video_time: total_time,
-            });
-
-            let progress = Arc::new(Mutex::new(0));
-            let report_progress = Arc::new(move || {
-                let mut p = progress.lock().unwrap();
-                *p = *p + 1;
-                self.progress_reporter.report(
-                    *p,
-                    frames_to_process as u64,
-                    &format!(
-                        "Processing frames of '{}'...",
-                        self.video_path.file_name().unwrap().to_string_lossy()
-                    ),
-                );
-            });
+        let results = Mutex::new(Vec::<(Matching<I>, FrameCandidates)>::new());
 
-            for (changed, frame, frame_time, frame_idx) in video_frames {
-                if !changed {
-                    report_progress();
-                    continue;
-                }
-
-                let results = results.clone();
-                let report_progress = report_progress.clone();
-                s.spawn_fifo(move |_s| {
-                    let matching = self.match_images_with_frame(frame, frame_time, frame_idx);
-
-                    let mut list = results.lock().unwrap();
-                    list.push(matching);
-                    report_progress();
+        let interval = self.matcher.capture_interval();
+        let mut vid = VideoCaptureIter::open(&self.video_path, interval);
+        let total_time = vid.total_time();
+        let total_frames = vid.total_frames();
+        // Every sampled frame reports progress once, whether it is matched or not.
+        // With scene change sampling, these are the probed frames.
+        let frames_to_process = (total_time.as_secs_f64() / interval.as_secs_f64()) as u32;
+
+        let progress = Mutex::new(0);
+        let report_progress = || {
+            let mut p = progress.lock().unwrap();
+            *p = *p + 1;
+            self.progress_reporter.report(
+                *p,
+                frames_to_process as u64,
+                &format!(
+                    "Processing frames of '{}'...",
+                    self.video_path.file_name().unwrap().to_string_lossy()
+                ),
+            );
+        };
+
+        // Long videos are decoded concurrently in time ranges, on dedicated threads,
+        // so that decoding does not block the rayon threads that match the frames.
+        let range_count = (frames_to_process as usize / MIN_SAMPLED_FRAMES_PER_RANGE)
+            .clamp(1, rayon::current_num_threads());
+        let ranges = split_time_ranges(total_time, interval, range_count);
+        // Decoders wait while all workers are busy, instead of queuing up full-resolution frames.
+        let (frames, queued_frames) = sync_channel(rayon::current_num_threads());
+        std::thread::scope(|decoders| {
+            for range in ranges {
+                let frames = frames.clone();
+                let report_progress = &report_progress;
+                decoders.spawn(move || {
+                    for (changed, frame, frame_time, frame_idx) in
+                        self.video_frames(interval, range)
+                    {
+                        if changed {
+                            frames.send((frame, frame_time, frame_idx)).unwrap();
+                        } else {
+                            report_progress();
+                        }
+                    }
                 });
             }
+            // The queue ends once all decoders are done.
+            drop(frames);
+
+            queued_frames
+                .into_iter()
+                .par_bridge()
+                .for_each(|(frame, frame_time, frame_idx)| {
+                    let result = self
+                        .matcher
+                        .match_images_with_frame(frame, frame_time, frame_idx);
+                    results.lock().unwrap().push(result);
+                    report_progress();
+                });
         });
 
         self.progress_reporter.report(
@@ -226,8 +437,16 @@ impl<I: MatchableImage + Send + Copy + Eq> VideoMatcherTask<I> for OpenCVVideoMa
             &format!("Finished!"),
         );
 
-        let mut mappings = results.lock().unwrap().clone();
-        mappings.sort_by_key(|m| m.video_time);
+        let mut results = results.into_inner().unwrap();
+        results.sort_by_key(|(m, _)| m.video_time);
+        let mut mappings = self.smooth(results);
+        // Add a matching to indicate the last frame.
+        mappings.push(Matching {
+            image: None,
+            video_frame_idx: total_frames as usize,
+            video_time: total_time,
+            thumbnail: None,
+        });
         let mut cleaned_mappings = Vec::new();
         let mut last_mapping: Option<Matching<I>> = None;
 
@@ -245,25 +464,109 @@ impl<I: MatchableImage + Send + Copy + Eq> VideoMatcherTask<I> for OpenCVVideoMa
     }
 }
 
+/// Splitting a video into time ranges only pays off if every range contains at least this many sampled frames.
+const MIN_SAMPLED_FRAMES_PER_RANGE: usize = 50;
+
 impl<I: MatchableImage + Send + Copy + Eq> OpenCVVideoMatcherTask<I> {
+    /// Returns the sampled frames within the given time range and whether they need to be matched.
+    fn video_frames(
+        &self,
+        interval: Duration,
+        range: Range<Duration>,
+    ) -> Box<dyn Iterator<Item = (bool, Mat, Duration, usize)>> {
+        // Ranges after the first one start with the preceding sampled frame,
+        // so that the first frame of the range is compared with it just like in a single pass.
+        // The preceding frame itself belongs to the previous range.
+        // For scene changes, the preceding frame counts as a cut, since a cut at the end of the previous range
+        // might not have settled there. This matches the first stable frame of every range,
+        // which at worst repeats the image of the previous range.
+        let (start, skip) = if range.start > Duration::default() {
+            (range.start.saturating_sub(interval), 1)
+        } else {
+            (Duration::default(), 0)
+        };
+        let vid =
+            VideoCaptureIter::open_with_decoding(&self.video_path, interval, self.matcher.decoding)
+                .with_range(start..range.end);
+
+        let change_threshold = self.matcher.params.frame_change_threshold;
+        match self.matcher.sampling {
+            Sampling::Interval => Box::new(MarkSimilarIter::new(vid, change_threshold).skip(skip)),
+            Sampling::SceneChanges { .. } => Box::new(
+                SceneChangeIter::new(vid, change_threshold, self.matcher.interval).skip(skip),
+            ),
+        }
+    }
+
+    /// Replaces the images of the given matchings (sorted by time) with the most likely sequence of images.
+    fn smooth(&self, results: Vec<(Matching<I>, FrameCandidates)>) -> Vec<Matching<I>> {
+        let params = match &self.matcher.smoothing {
+            Some(params) => params,
+            None => return results.into_iter().map(|(m, _)| m).collect(),
+        };
+
+        let candidates: Vec<FrameCandidates> = results.iter().map(|(_, c)| c.clone()).collect();
+        let positions: Vec<_> = self
+            .matcher
+            .images
+            .iter()
+            .map(|img| img.source_img.deck_position())
+            .collect();
+        let smoothed = smooth_matchings(&candidates, &next_pages(&positions), params);
+
+        results
+            .into_iter()
+            .zip(smoothed)
+            .map(|((matching, _), idx)| Matching {
+                image: idx.map(|idx| self.matcher.images[idx].source_img),
+                ..matching
+            })
+            .collect()
+    }
+}
+
+impl<I: MatchableImage + Send + Copy + Eq> OpenCVVideoMatcher<I> {
+    /// The interval at which frames are read from the video.
+    fn capture_interval(&self) -> Duration {
+        match self.sampling {
+            Sampling::Interval => self.interval,
+            Sampling::SceneChanges { probe_interval } => probe_interval,
+        }
+    }
+
+    fn overlay_group_of(&self, image_idx: usize) -> Option<&OverlayGroup> {
+        self.overlay_groups
+            .iter()
+            .find(|g| g.members.contains(&image_idx))
+    }
+
+    /// Returns the best matching image of the frame and the scores of all candidate images.
     fn match_images_with_frame(
         &self,
         frame: Mat,
         frame_time: Duration,
         frame_idx: usize,
-    ) -> Matching<I> {
+    ) -> (Matching<I>, FrameCandidates) {
         let mut flann = self
             .shared_flanns
             .get_or(|| {
                 RefCell::new(FlannMatcher::new(
+                    self.feature_extractor.descriptor_kind(),
                     self.images.iter().map(|f| f.descriptors.clone()),
                 ))
             })
             .borrow_mut();
 
-        let frame_info =
-            FEATURE_EXTRACTOR.with(|e| e.borrow_mut().find_keypoints_and_descriptors(&frame));
-        let matches = flann.knn_match(&frame_info.descriptors, 30);
+        let thumbnail = to_thumbnail_jpeg(&frame);
+        // The frame is matched as it is after preprocessing, keypoints refer to the preprocessed frame.
+        let preprocessed = self.preprocessing.apply(&frame);
+        let frame = preprocessed.image;
+        let frame_info = self
+            .feature_extractors
+            .get_or(|| RefCell::new(FeatureExtractor::new(&self.feature_extractor)))
+            .borrow_mut()
+            .find_keypoints_and_descriptors(&frame, preprocessed.mask.as_ref());
+        let matches = flann.knn_match(&frame_info.descriptors, self.params.knn_neighbors);
 
         let mut best_matches_by_slide_idx = HashMap::<usize, Vec<_>>::new();
 
@@ -272,7 +575,7 @@ impl<I: MatchableImage + Send + Copy + Eq> OpenCVVideoMatcherTask<I> {
             for dmatch in matched_descriptors.into_iter() {
                 // Add a match for all descriptors the query descriptor has a good match with.
                 assert!(best.query_idx == dmatch.query_idx);
-                if dmatch.distance < best.distance * 1.05 {
+                if dmatch.distance < best.distance * self.params.distance_tolerance as f32 {
                     best_matches_by_slide_idx
                         .entry(dmatch.source)
                         .or_default()
@@ -291,15 +594,18 @@ impl<I: MatchableImage + Send + Copy + Eq> OpenCVVideoMatcherTask<I> {
 
         let mut rated_best_matches: Vec<_> = best_matches
             .into_iter()
-            // Only consider the best 40 slides
-            .take(40)
+            // Only consider the slides with the most matches
+            .take(self.params.max_candidates)
             .map(|(slide_info, matches)| {
-                let result = Transformation2D::estimate_affine(matches.iter().map(|m| {
-                    (
-                        slide_info.keypoints.get(m.train_idx as usize).unwrap().pt,
-                        frame_info.keypoints.get(m.query_idx as usize).unwrap().pt,
-                    )
-                }));
+                let result = Transformation2D::estimate(
+                    self.transformation_model,
+                    matches.iter().map(|m| {
+                        (
+                            slide_info.keypoints.get(m.train_idx as usize).unwrap().pt,
+                            frame_info.keypoints.get(m.query_idx as usize).unwrap().pt,
+                        )
+                    }),
+                );
                 let inlier_matches: Vec<_> = matches
                     .into_iter()
                     .zip(result.inlier_flags)
@@ -327,28 +633,28 @@ impl<I: MatchableImage + Send + Copy + Eq> OpenCVVideoMatcherTask<I> {
         */
 
         rated_best_matches.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap());
-        rated_best_matches.truncate(10);
+        rated_best_matches.truncate(self.params.max_rated_candidates);
         let best_rating = rated_best_matches.first().map_or(0.0, |v| v.2);
         // Keep all matches that have a competitive rating.
-        rated_best_matches.retain(|v| v.2 > 50.0 && v.2 / best_rating > 0.2);
+        rated_best_matches.retain(|v| {
+            v.2 > self.params.min_inliers && v.2 / best_rating > self.params.min_relative_inliers
+        });
 
         let mut rated_best_matches = rated_best_matches
             .into_iter()
             .map(|(slide_info, matches, _rating, transformation)| {
-                let mut frame_proj = Mat::default();
-                warp_affine(
-                    &frame,
-                    &mut frame_proj,
-                    &transformation.mat,
-                    slide_info.img.size().unwrap(),
-                    WARP_INVERSE_MAP,
-                    0,
-                    Scalar::new(0.0, 0.0, 0.0, 0.0),
-                )
-                .unwrap();
+                let frame_proj =
+                    transformation.warp_inverse(&frame, slide_info.img.size().unwrap());
 
                 let frame_proj2 = to_small_image(&frame_proj);
-                let similarity = compute_similarity(&frame_proj2, &slide_info.small_img);
+                // Masked regions of the frame are not compared with the slide.
+                let mask_proj = preprocessed.mask.as_ref().map(|mask| {
+                    to_small_mask(
+                        &transformation.warp_inverse(mask, slide_info.img.size().unwrap()),
+                    )
+                });
+                let similarity =
+                    compute_similarity(&frame_proj2, &slide_info.small_img, mask_proj.as_ref());
                 /*
                 println!("similarity: {}, rating: {}", similarity, rating);
                 imshow(&"test", &frame_proj2).unwrap();
@@ -363,12 +669,27 @@ impl<I: MatchableImage + Send + Copy + Eq> OpenCVVideoMatcherTask<I> {
                 let mut out = Mat::default().unwrap();
                 hconcat(&images, &mut out).unwrap();*/
 
-                (slide_info, matches, similarity, transformation)
+                (slide_info, matches, similarity, frame_proj2)
             })
             .collect::<Vec<_>>();
 
         rated_best_matches.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap());
 
+        // The pages of an overlay group look nearly the same, only their revealed regions tell them apart.
+        if let Some(group) = rated_best_matches
+            .first()
+            .and_then(|v| self.overlay_group_of(v.0.idx))
+        {
+            let best_member =
+                group.best_member(&rated_best_matches[0].3, |idx| &self.images[idx].small_img);
+            rated_best_matches[0].0 = &self.images[best_member];
+            let mut position = 0;
+            rated_best_matches.retain(|v| {
+                position += 1;
+                position == 1 || !group.members.contains(&v.0.idx)
+            });
+        }
+
         /*
         println!(
             "best sim: {:?} best rating: {}",
@@ -377,16 +698,26 @@ impl<I: MatchableImage + Send + Copy + Eq> OpenCVVideoMatcherTask<I> {
         );
         */
 
-        // At least a similarity of 0.5 is required
-        rated_best_matches.retain(|v| v.2 > 0.5);
+        rated_best_matches.retain(|v| v.2 > self.params.min_similarity as f32);
+
+        // Smoothing only chooses between the images that are similar enough to the frame.
+        let candidates = FrameCandidates {
+            scores: rated_best_matches
+                .iter()
+                .filter(|v| v.2 > 0.0)
+                .map(|v| (v.0.idx, v.2.min(1.0) as f64))
+                .collect(),
+        };
 
         let first = rated_best_matches.into_iter().next();
 
-        Matching {
+        let matching = Matching {
             video_frame_idx: frame_idx,
             video_time: frame_time,
             image: first.map(|v| v.0.source_img),
-        }
+            thumbnail: Some(thumbnail),
+        };
+        (matching, candidates)
 
         /*
         if let Some((slide_info, _matches, _rating, _transformation, out)) = first {
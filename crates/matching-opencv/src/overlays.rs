@@ -0,0 +1,166 @@
+use opencv::{
+    core::{absdiff, bitwise_or, count_non_zero, mean_std_dev, no_array, norm2, NORM_L2},
+    imgproc::{cvt_color, threshold, COLOR_BGR2GRAY, THRESH_BINARY},
+    prelude::*,
+};
+
+/// Pixels whose gray values differ by more than this are considered changed.
+const CHANGE_THRESHOLD: f64 = 40.0;
+/// An overlay only reveals a small part of the page.
+const MAX_CHANGED_RATIO: f64 = 0.3;
+/// The revealed region must be empty on the previous page,
+/// i.e. the gray values there must not vary more than this.
+const MAX_EMPTY_STD_DEV: f64 = 12.0;
+
+/// Consecutive pages where every page shows the content of the previous page and reveals some more,
+/// like the overlays of a beamer frame.
+pub struct OverlayGroup {
+    /// The indices of the pages in order.
+    pub members: Vec<usize>,
+    /// The union of all regions that are revealed within the group.
+    /// All members have the same size.
+    pub revealed_mask: Mat,
+}
+
+unsafe impl Send for OverlayGroup {}
+unsafe impl Sync for OverlayGroup {}
+
+impl OverlayGroup {
+    /// Returns the member that fits the given image (scaled to the size of the members) best
+    /// within the revealed regions.
+    pub fn best_member<'a>(&self, img: &Mat, member_imgs: impl Fn(usize) -> &'a Mat) -> usize {
+        self.members
+            .iter()
+            .map(|&idx| {
+                let error = norm2(img, member_imgs(idx), NORM_L2, &self.revealed_mask).unwrap();
+                (idx, error)
+            })
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .map(|(idx, _)| idx)
+            .unwrap()
+    }
+}
+
+/// Finds overlay groups among the given (small) page images, which must be in page order.
+pub fn detect_overlay_groups(imgs: &[&Mat]) -> Vec<OverlayGroup> {
+    let mut groups = Vec::new();
+    let mut current: Option<OverlayGroup> = None;
+
+    for idx in 1..imgs.len() {
+        match revealed_region(imgs[idx - 1], imgs[idx]) {
+            Some(mask) => match &mut current {
+                Some(group) => {
+                    let mut revealed_mask = Mat::default();
+                    bitwise_or(
+                        &group.revealed_mask,
+                        &mask,
+                        &mut revealed_mask,
+                        &no_array().unwrap(),
+                    )
+                    .unwrap();
+                    group.revealed_mask = revealed_mask;
+                    group.members.push(idx);
+                }
+                None => {
+                    current = Some(OverlayGroup {
+                        members: vec![idx - 1, idx],
+                        revealed_mask: mask,
+                    })
+                }
+            },
+            None => groups.extend(current.take()),
+        }
+    }
+    groups.extend(current);
+
+    groups
+}
+
+fn to_gray(img: &Mat) -> Mat {
+    if img.channels().unwrap() == 1 {
+        return img.clone();
+    }
+    let mut gray = Mat::default();
+    cvt_color(img, &mut gray, COLOR_BGR2GRAY, 0).unwrap();
+    gray
+}
+
+/// Returns the mask of the region that `next` reveals, if `next` only adds content to `prev`.
+/// Identical pages reveal an empty region.
+fn revealed_region(prev: &Mat, next: &Mat) -> Option<Mat> {
+    if prev.size().unwrap() != next.size().unwrap() {
+        return None;
+    }
+    let prev = to_gray(prev);
+    let next = to_gray(next);
+
+    let mut diff = Mat::default();
+    absdiff(&prev, &next, &mut diff).unwrap();
+    let mut mask = Mat::default();
+    threshold(&diff, &mut mask, CHANGE_THRESHOLD, 255.0, THRESH_BINARY).unwrap();
+
+    let changed = count_non_zero(&mask).unwrap();
+    if changed == 0 {
+        return Some(mask);
+    }
+    if changed as f64 / mask.total().unwrap() as f64 > MAX_CHANGED_RATIO {
+        return None;
+    }
+
+    // Content that is replaced or moved is not an overlay.
+    let mut mean = Mat::default();
+    let mut std_dev = Mat::default();
+    mean_std_dev(&prev, &mut mean, &mut std_dev, &mask).unwrap();
+    if *std_dev.at::<f64>(0).unwrap() > MAX_EMPTY_STD_DEV {
+        return None;
+    }
+
+    Some(mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opencv::{
+        core::{Rect, Scalar, CV_8UC1},
+        imgproc::{rectangle, FILLED, LINE_8},
+    };
+
+    fn page(color: f64, boxes: &[Rect]) -> Mat {
+        let mut img =
+            Mat::new_rows_cols_with_default(100, 100, CV_8UC1, Scalar::all(color)).unwrap();
+        for &rect in boxes {
+            rectangle(&mut img, rect, Scalar::all(0.0), FILLED, LINE_8, 0).unwrap();
+        }
+        img
+    }
+
+    #[test]
+    fn test_detect_overlay_groups() {
+        let first = Rect::new(10, 10, 30, 10);
+        let second = Rect::new(10, 40, 30, 10);
+        let pages = vec![
+            page(255.0, &[]),
+            page(255.0, &[first]),
+            page(255.0, &[first, second]),
+            // Replaces everything.
+            page(0.0, &[]),
+            page(0.0, &[]),
+        ];
+
+        let groups = detect_overlay_groups(&pages.iter().collect::<Vec<_>>());
+        let members: Vec<_> = groups.iter().map(|g| g.members.clone()).collect();
+        assert_eq!(members, vec![vec![0, 1, 2], vec![3, 4]]);
+
+        let frame = pages[1].clone();
+        assert_eq!(groups[0].best_member(&frame, |idx| &pages[idx]), 1);
+    }
+
+    #[test]
+    fn test_moved_content_is_not_revealed() {
+        let prev = page(255.0, &[Rect::new(10, 10, 30, 10)]);
+        let next = page(255.0, &[Rect::new(10, 60, 30, 10)]);
+        assert!(revealed_region(&prev, &next).is_none());
+        assert!(revealed_region(&prev, &prev).is_some());
+    }
+}
@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+
+/// The thresholds that decide which image a video frame shows.
+///
+/// Missing fields are filled in with their defaults when deserialized,
+/// so a parameter file only needs to list the values it changes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MatchingParams {
+    /// The number of nearest image descriptors that are looked up for every frame descriptor.
+    pub knn_neighbors: i32,
+    /// A neighbour counts as a match if its distance is at most this factor of the best distance.
+    pub distance_tolerance: f64,
+    /// Only the images with the most matches are considered.
+    pub max_candidates: usize,
+    /// Only the candidates with the most inliers are compared pixel by pixel.
+    pub max_rated_candidates: usize,
+    /// The minimal number of inlier matches of a candidate.
+    pub min_inliers: f64,
+    /// The minimal number of inliers of a candidate relative to the best candidate.
+    pub min_relative_inliers: f64,
+    /// The minimal pixel similarity of the projected frame and the image.
+    pub min_similarity: f64,
+    /// Frames that are at least this similar to the previous frame are not matched again.
+    pub frame_change_threshold: f64,
+}
+
+impl Default for MatchingParams {
+    fn default() -> Self {
+        MatchingParams {
+            knn_neighbors: 30,
+            distance_tolerance: 1.05,
+            max_candidates: 40,
+            max_rated_candidates: 10,
+            min_inliers: 50.0,
+            min_relative_inliers: 0.2,
+            min_similarity: 0.5,
+            frame_change_threshold: 0.98,
+        }
+    }
+}
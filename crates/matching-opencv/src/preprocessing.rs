@@ -0,0 +1,223 @@
+use opencv::{
+    core::{
+        find_non_zero, min_max_loc, no_array, Point, Rect, Scalar, Size, ToInputArray, Vector,
+        CV_8UC1,
+    },
+    imgproc::{
+        bounding_rect, cvt_color, rectangle, resize, threshold, COLOR_BGR2GRAY, FILLED, INTER_AREA,
+        INTER_LINEAR, LINE_8, THRESH_BINARY,
+    },
+    prelude::*,
+};
+
+/// A region of a frame in coordinates relative to the frame size (`0.0..=1.0`),
+/// so that it does not depend on the resolution of the video.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RelativeRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PreprocessingStep {
+    /// Blends the two fields of interlaced recordings to remove combing artifacts.
+    Deinterlace,
+    /// Ignores the given regions of the frame, e.g. a webcam overlay or browser chrome.
+    MaskRegions(Vec<RelativeRect>),
+    /// Crops black bars around the content.
+    CropLetterbox,
+    /// Stretches the brightness of the frame to the full range.
+    /// Only the unmasked pixels determine how the brightness is stretched.
+    NormalizeContrast,
+}
+
+/// A chain of steps that prepares video frames for feature extraction.
+/// The steps are applied in the given order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Preprocessing {
+    pub steps: Vec<PreprocessingStep>,
+}
+
+pub struct PreprocessedFrame {
+    /// Masked pixels are kept, so that every step treats them the same.
+    pub image: Mat,
+    /// Features are only detected and pixels are only compared where the mask is non-zero.
+    /// `None` if the entire image is used.
+    pub mask: Option<Mat>,
+}
+
+/// Pixels darker than this belong to letterbox bars.
+const LETTERBOX_THRESHOLD: f64 = 16.0;
+
+impl Preprocessing {
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn apply(&self, frame: &Mat) -> PreprocessedFrame {
+        let mut result = PreprocessedFrame {
+            image: frame.clone(),
+            mask: None,
+        };
+        for step in &self.steps {
+            match step {
+                PreprocessingStep::Deinterlace => result.image = deinterlace(&result.image),
+                PreprocessingStep::MaskRegions(regions) => mask_regions(&mut result, regions),
+                PreprocessingStep::CropLetterbox => crop_letterbox(&mut result),
+                PreprocessingStep::NormalizeContrast => normalize_contrast(&mut result),
+            }
+        }
+        result
+    }
+}
+
+fn deinterlace(image: &Mat) -> Mat {
+    let size = image.size().unwrap();
+    // Downscaling by two averages the lines of both fields.
+    let mut half = Mat::default();
+    resize(
+        image,
+        &mut half,
+        Size::new(size.width, size.height / 2),
+        0.0,
+        0.0,
+        INTER_AREA,
+    )
+    .unwrap();
+    let mut result = Mat::default();
+    resize(&half, &mut result, size, 0.0, 0.0, INTER_LINEAR).unwrap();
+    result
+}
+
+fn mask_regions(frame: &mut PreprocessedFrame, regions: &[RelativeRect]) {
+    let size = frame.image.size().unwrap();
+    let mask = frame.mask.get_or_insert_with(|| {
+        Mat::new_rows_cols_with_default(size.height, size.width, CV_8UC1, Scalar::all(255.0))
+            .unwrap()
+    });
+    for region in regions {
+        let rect = Rect::new(
+            (region.x * size.width as f64).round() as i32,
+            (region.y * size.height as f64).round() as i32,
+            (region.width * size.width as f64).round() as i32,
+            (region.height * size.height as f64).round() as i32,
+        );
+        rectangle(mask, rect, Scalar::all(0.0), FILLED, LINE_8, 0).unwrap();
+    }
+}
+
+fn to_gray(image: &Mat) -> Mat {
+    if image.channels().unwrap() == 1 {
+        return image.clone();
+    }
+    let mut gray = Mat::default();
+    cvt_color(image, &mut gray, COLOR_BGR2GRAY, 0).unwrap();
+    gray
+}
+
+/// The brightness range is measured on a grayscale copy, since OpenCV only supports masks for single channel images.
+/// The same scale and shift are applied to all channels and pixels, including the masked ones.
+fn normalize_contrast(frame: &mut PreprocessedFrame) {
+    let no_mask = no_array().unwrap();
+    let mask: &dyn ToInputArray = match &frame.mask {
+        Some(mask) => mask,
+        None => &no_mask,
+    };
+    let mut min = 0.0;
+    let mut max = 0.0;
+    min_max_loc(
+        &to_gray(&frame.image),
+        &mut min,
+        &mut max,
+        &mut Point::default(),
+        &mut Point::default(),
+        mask,
+    )
+    .unwrap();
+    if max <= min {
+        return;
+    }
+
+    let scale = 255.0 / (max - min);
+    let mut normalized = Mat::default();
+    frame
+        .image
+        .convert_to(&mut normalized, -1, scale, -min * scale)
+        .unwrap();
+    frame.image = normalized;
+}
+
+fn crop_letterbox(frame: &mut PreprocessedFrame) {
+    let gray = to_gray(&frame.image);
+    let mut content = Mat::default();
+    threshold(
+        &gray,
+        &mut content,
+        LETTERBOX_THRESHOLD,
+        255.0,
+        THRESH_BINARY,
+    )
+    .unwrap();
+    let mut points = Vector::<Point>::new();
+    find_non_zero(&content, &mut points).unwrap();
+    if points.is_empty() {
+        // A black frame
+        return;
+    }
+
+    let size = frame.image.size().unwrap();
+    let rect = bounding_rect(&points).unwrap();
+    // Dark slides are not letterboxed.
+    if rect.size() == size || rect.area() < size.area() / 4 {
+        return;
+    }
+
+    frame.image = Mat::roi(&frame.image, rect).unwrap().clone();
+    if let Some(mask) = &frame.mask {
+        frame.mask = Some(Mat::roi(mask, rect).unwrap().clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opencv::core::{Vec3b, CV_8UC3};
+
+    #[test]
+    fn test_normalize_masked_color_frame() {
+        let mut frame =
+            Mat::new_rows_cols_with_default(100, 100, CV_8UC3, Scalar::all(100.0)).unwrap();
+        let left = Rect::new(0, 0, 50, 100);
+        rectangle(&mut frame, left, Scalar::all(150.0), FILLED, LINE_8, 0).unwrap();
+        // A bright webcam overlay in the right quarter, which must not affect the normalization.
+        let overlay = Rect::new(75, 0, 25, 100);
+        rectangle(&mut frame, overlay, Scalar::all(250.0), FILLED, LINE_8, 0).unwrap();
+
+        let preprocessing = Preprocessing {
+            steps: vec![
+                PreprocessingStep::MaskRegions(vec![RelativeRect {
+                    x: 0.75,
+                    y: 0.0,
+                    width: 0.25,
+                    height: 1.0,
+                }]),
+                PreprocessingStep::NormalizeContrast,
+            ],
+        };
+        let result = preprocessing.apply(&frame);
+
+        assert_eq!(result.image.channels().unwrap(), 3);
+        let pixel = |x: i32| *result.image.at_2d::<Vec3b>(50, x).unwrap();
+        assert_eq!(pixel(10), Vec3b::all(255));
+        assert_eq!(pixel(60), Vec3b::all(0));
+        // Masked pixels are scaled like all others instead of being cleared.
+        assert_eq!(pixel(90), Vec3b::all(255));
+        assert_eq!(
+            *result.mask.unwrap().at_2d::<u8>(50, 90).unwrap(),
+            0,
+            "the overlay is masked"
+        );
+    }
+}
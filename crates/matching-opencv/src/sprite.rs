@@ -0,0 +1,95 @@
+use opencv::{
+    core::{hconcat, vconcat, Scalar, Size, StsError, Vector, CV_8UC3},
+    imgcodecs::{imdecode, imencode, IMREAD_COLOR},
+    imgproc::{resize, INTER_AREA},
+    prelude::*,
+    types::VectorOfMat,
+};
+
+pub struct SpriteSheet {
+    /// The JPEG encoded sprite sheet.
+    pub jpeg: Vec<u8>,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub columns: usize,
+}
+
+impl SpriteSheet {
+    /// Returns the position of the tile with the given index as (x, y).
+    pub fn tile_position(&self, idx: usize) -> (u32, u32) {
+        let column = (idx % self.columns) as u32;
+        let row = (idx / self.columns) as u32;
+        (column * self.tile_width, row * self.tile_height)
+    }
+}
+
+/// Arranges the given JPEG encoded thumbnails row by row in a single image.
+/// All thumbnails are scaled to the size of the first one.
+pub fn create_sprite_sheet(thumbnails: &[&[u8]], columns: usize) -> opencv::Result<SpriteSheet> {
+    let tiles = thumbnails
+        .iter()
+        .enumerate()
+        .map(|(idx, jpeg)| {
+            let tile = imdecode(&Vector::<u8>::from_slice(jpeg), IMREAD_COLOR)?;
+            // Invalid images are decoded as empty images.
+            if tile.empty()? {
+                return Err(opencv::Error::new(
+                    StsError,
+                    format!("Thumbnail {} cannot be decoded", idx),
+                ));
+            }
+            Ok(tile)
+        })
+        .collect::<opencv::Result<Vec<Mat>>>()?;
+    let tile_size = match tiles.first() {
+        Some(tile) => tile.size()?,
+        None => Size::new(1, 1),
+    };
+
+    let mut rows = VectorOfMat::new();
+    for row_tiles in tiles.chunks(columns) {
+        let mut row = VectorOfMat::new();
+        for tile in row_tiles {
+            if tile.size()? == tile_size {
+                row.push(tile.clone());
+            } else {
+                let mut scaled_tile = Mat::default();
+                resize(&tile, &mut scaled_tile, tile_size, 0.0, 0.0, INTER_AREA)?;
+                row.push(scaled_tile);
+            }
+        }
+        // Fill up the last row, so that all rows have the same width.
+        for _ in row_tiles.len()..columns {
+            row.push(Mat::new_size_with_default(
+                tile_size,
+                CV_8UC3,
+                Scalar::all(0.0),
+            )?);
+        }
+        let mut row_mat = Mat::default();
+        hconcat(&row, &mut row_mat)?;
+        rows.push(row_mat);
+    }
+
+    let mut sheet = Mat::default();
+    if rows.is_empty() {
+        sheet = Mat::new_size_with_default(tile_size, CV_8UC3, Scalar::all(0.0))?;
+    } else {
+        vconcat(&rows, &mut sheet)?;
+    }
+
+    let mut buf = Vector::<u8>::new();
+    if !imencode(".jpg", &sheet, &mut buf, &Vector::new())? {
+        return Err(opencv::Error::new(
+            StsError,
+            "The sprite sheet cannot be encoded".to_string(),
+        ));
+    }
+
+    Ok(SpriteSheet {
+        jpeg: buf.to_vec(),
+        tile_width: tile_size.width as u32,
+        tile_height: tile_size.height as u32,
+        columns,
+    })
+}
@@ -1,19 +1,53 @@
 use opencv::{
     prelude::*,
-    videoio::{VideoCapture, CAP_PROP_FPS, CAP_PROP_FRAME_COUNT, CAP_PROP_POS_FRAMES},
+    videoio::{
+        VideoCapture, CAP_PROP_FPS, CAP_PROP_FRAME_COUNT, CAP_PROP_FRAME_HEIGHT,
+        CAP_PROP_FRAME_WIDTH, CAP_PROP_POS_FRAMES, CAP_PROP_POS_MSEC,
+    },
 };
-use std::{path::Path, rc::Rc, time::Duration};
+use std::{ops::Range, path::Path, rc::Rc, time::Duration};
 
 use super::image_utils::{compute_similarity, to_small_image};
 
+/// How the frames between two sampled frames are skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Decoding {
+    /// Decodes every frame of the video.
+    Sequential,
+    /// Seeks to the next sampled frame if it is far enough ahead,
+    /// so that only the frames from the preceding keyframe on are decoded.
+    #[default]
+    Seek,
+}
+
+/// Seeking only pays off if it skips at least this much of the video.
+const MIN_SEEK_DISTANCE: Duration = Duration::from_secs(2);
+/// The duration is measured by decoding the frames from this long before the nominal end.
+const DURATION_TAIL: Duration = Duration::from_secs(2);
+
+/// Yields a frame at every interval, with its time and index.
+///
+/// Times are taken from the timestamps of the frames,
+/// so they are correct for variable frame rate recordings, where `frame index / fps` drifts.
 pub struct VideoCaptureIter {
     video: VideoCapture,
     fps: f64,
     interval: Duration,
+    decoding: Decoding,
+    /// The time of the next frame that is yielded. Frames are yielded at multiples of the interval.
+    next_time: Duration,
+    /// Only frames in this time range are yielded.
+    range: Range<Duration>,
+    /// The time of the last decoded frame.
+    position: Option<Duration>,
 }
 
 impl VideoCaptureIter {
     pub fn open(path: &Path, interval: Duration) -> Self {
+        VideoCaptureIter::open_with_decoding(path, interval, Decoding::default())
+    }
+
+    pub fn open_with_decoding(path: &Path, interval: Duration, decoding: Decoding) -> Self {
         let video = opencv::videoio::VideoCapture::from_file(
             &path.to_string_lossy(),
             0, //CAP_DSHOW
@@ -24,15 +58,83 @@ impl VideoCaptureIter {
             video,
             fps,
             interval,
+            decoding,
+            next_time: Duration::default(),
+            range: Duration::default()..Duration::MAX,
+            position: None,
         }
     }
 
+    /// Only yields the frames within the given time range.
+    /// The yielded frames are the same as without a range, i.e. one at every interval from the start of the video.
+    pub fn with_range(mut self, range: Range<Duration>) -> Self {
+        let first = (range.start.as_secs_f64() / self.interval.as_secs_f64()).ceil();
+        self.next_time = self.interval.mul_f64(first);
+        if self.next_time > Duration::default() {
+            self.seek(self.next_time);
+        }
+        self.range = self.next_time..range.end;
+        self
+    }
+
+    /// The nominal number of frames. Only an estimate for variable frame rate recordings.
     pub fn total_frames(&self) -> f64 {
         self.video.get(CAP_PROP_FRAME_COUNT).unwrap()
     }
 
-    pub fn total_time(&self) -> Duration {
-        Duration::from_secs_f64(self.video.get(CAP_PROP_FRAME_COUNT).unwrap() / self.fps)
+    /// The time of the end of the video, i.e. the time of the last frame plus its duration.
+    /// Must be called before iterating, as it seeks back to the start of the video.
+    pub fn total_time(&mut self) -> Duration {
+        // OpenCV does not expose the duration of the container, and the nominal duration `frame count / fps`
+        // is wrong for variable frame rate recordings. So the timestamp of the last frame is measured.
+        let nominal = Duration::from_secs_f64(self.total_frames() / self.fps);
+        let mut start = nominal.saturating_sub(DURATION_TAIL);
+        let mut step = DURATION_TAIL;
+        let mut last = loop {
+            self.seek(start);
+            if let Some(time) = self.grab() {
+                break time;
+            }
+            // The nominal end is after the actual end.
+            if start == Duration::default() {
+                return nominal;
+            }
+            start = start.saturating_sub(step);
+            step *= 2;
+        };
+        while let Some(time) = self.grab() {
+            last = time;
+        }
+
+        self.seek(Duration::default());
+        self.position = None;
+        last + Duration::from_secs_f64(1.0 / self.fps)
+    }
+
+    /// Returns the width and height of the frames in pixels.
+    pub fn frame_size(&self) -> (u32, u32) {
+        (
+            self.video.get(CAP_PROP_FRAME_WIDTH).unwrap() as u32,
+            self.video.get(CAP_PROP_FRAME_HEIGHT).unwrap() as u32,
+        )
+    }
+
+    fn seek(&mut self, time: Duration) {
+        // Seeking by time is also accurate for variable frame rates, unlike seeking by frame index.
+        self.video
+            .set(CAP_PROP_POS_MSEC, time.as_secs_f64() * 1000.0)
+            .unwrap();
+    }
+
+    /// Decodes the next frame and returns its time.
+    fn grab(&mut self) -> Option<Duration> {
+        if !self.video.grab().unwrap() {
+            return None;
+        }
+        let time =
+            Duration::from_secs_f64(self.video.get(CAP_PROP_POS_MSEC).unwrap().max(0.0) / 1000.0);
+        self.position = Some(time);
+        Some(time)
     }
 }
 
@@ -40,18 +142,31 @@ impl Iterator for VideoCaptureIter {
     type Item = (Mat, Duration, usize);
 
     fn next(&mut self) -> Option<(Mat, Duration, /* frame */ usize)> {
+        if self.decoding == Decoding::Seek {
+            if let Some(position) = self.position {
+                if self.next_time >= position + MIN_SEEK_DISTANCE {
+                    self.seek(self.next_time);
+                }
+            }
+        }
+
+        // Frame timestamps are not exact multiples of the interval.
+        let tolerance = Duration::from_secs_f64(0.5 / self.fps);
         let mut frame = Mat::default();
         loop {
             let frame_idx = self.video.get(CAP_PROP_POS_FRAMES).unwrap();
-            let time_passed = Duration::from_secs_f64(frame_idx / self.fps);
-
-            if !self.video.grab().unwrap() {
+            let time = self.grab()?;
+            if time >= self.range.end {
                 return None;
             }
 
-            if frame_idx % (self.fps * self.interval.as_secs_f64()).floor() < 1.0 {
+            // Seeking might end up a bit earlier.
+            if time + tolerance >= self.next_time {
                 self.video.retrieve(&mut frame, 0).unwrap();
-                return Some((frame, time_passed, frame_idx as usize));
+                while self.next_time <= time + tolerance {
+                    self.next_time += self.interval;
+                }
+                return Some((frame, time, frame_idx as usize));
             }
         }
     }
@@ -63,16 +178,19 @@ where
 {
     iter: I,
     last_frame: Option<Rc<Mat>>,
+    /// Frames that are at least this similar to the previous frame are marked as unchanged.
+    change_threshold: f64,
 }
 
 impl<I> MarkSimilarIter<I>
 where
     I: Iterator<Item = (Mat, Duration, usize)>,
 {
-    pub fn new(iter: I) -> Self {
+    pub fn new(iter: I, change_threshold: f64) -> Self {
         return MarkSimilarIter {
             iter,
             last_frame: None,
+            change_threshold,
         };
     }
 }
@@ -87,7 +205,7 @@ where
         if let Some((frame, frame_time, frame_idx)) = self.iter.next() {
             let scaled_frame = to_small_image(&frame);
             let similarity = if let Some(last_frame) = &self.last_frame {
-                compute_similarity(last_frame, &scaled_frame)
+                compute_similarity(last_frame, &scaled_frame, None)
             } else {
                 0.0
             };
@@ -95,9 +213,255 @@ where
             let rc = Rc::new(scaled_frame);
 
             self.last_frame = Some(rc.clone());
-            Some((similarity < 0.98, frame, frame_time, frame_idx))
+            Some((
+                similarity < self.change_threshold as f32,
+                frame,
+                frame_time,
+                frame_idx,
+            ))
         } else {
             None
         }
     }
 }
+
+/// Splits a video into `count` time ranges that contain about the same number of sampled frames.
+/// The ranges start at sampled frames, i.e. multiples of `interval`.
+pub fn split_time_ranges(
+    total_time: Duration,
+    interval: Duration,
+    count: usize,
+) -> Vec<Range<Duration>> {
+    let sampled_frames = (total_time.as_secs_f64() / interval.as_secs_f64()).ceil() as usize;
+    let count = count.clamp(1, sampled_frames.max(1));
+    (0..count)
+        .map(|i| {
+            let start = interval * (sampled_frames * i / count) as u32;
+            let end = if i + 1 == count {
+                total_time
+            } else {
+                interval * (sampled_frames * (i + 1) / count) as u32
+            };
+            start..end
+        })
+        .collect()
+}
+
+/// How the frames of a video are sampled for matching.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Sampling {
+    /// Matches a frame at every interval, unless it looks like the previous one.
+    #[default]
+    Interval,
+    /// Examines frames at the probe interval and only matches the first stable frame after a visual cut.
+    /// While the video keeps changing (e.g. a webcam overlay), a frame is matched at every interval.
+    SceneChanges { probe_interval: Duration },
+}
+
+/// Marks the first stable frame after every visual cut as changed.
+/// A frame is stable if it is similar to the previous frame.
+/// Changed frames are reported at the time of the cut.
+pub struct SceneChangeIter<I>
+where
+    I: Iterator<Item = (Mat, Duration, usize)>,
+{
+    iter: I,
+    last_frame: Option<Mat>,
+    /// Frames that are at least this similar to the previous frame are stable.
+    change_threshold: f64,
+    /// A frame is marked after the video has been unstable for this long.
+    max_unstable: Duration,
+    /// The time and index of the frame where the current cut started.
+    cut: Option<(Duration, usize)>,
+}
+
+impl<I> SceneChangeIter<I>
+where
+    I: Iterator<Item = (Mat, Duration, usize)>,
+{
+    pub fn new(iter: I, change_threshold: f64, max_unstable: Duration) -> Self {
+        SceneChangeIter {
+            iter,
+            last_frame: None,
+            change_threshold,
+            max_unstable,
+            cut: None,
+        }
+    }
+}
+
+impl<I> Iterator for SceneChangeIter<I>
+where
+    I: Iterator<Item = (Mat, Duration, usize)>,
+{
+    type Item = (bool, Mat, Duration, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (frame, frame_time, frame_idx) = self.iter.next()?;
+        let scaled_frame = to_small_image(&frame);
+        let stable = match &self.last_frame {
+            Some(last_frame) => {
+                compute_similarity(last_frame, &scaled_frame, None) >= self.change_threshold as f32
+            }
+            // The start of the video counts as a cut.
+            None => false,
+        };
+        self.last_frame = Some(scaled_frame);
+
+        match self.cut {
+            Some((cut_time, cut_idx)) if stable => {
+                self.cut = None;
+                Some((true, frame, cut_time, cut_idx))
+            }
+            Some((cut_time, _)) if frame_time >= cut_time + self.max_unstable => {
+                // The video does not settle, match it anyway.
+                self.cut = Some((frame_time, frame_idx));
+                Some((true, frame, frame_time, frame_idx))
+            }
+            Some(_) => Some((false, frame, frame_time, frame_idx)),
+            None => {
+                if !stable {
+                    self.cut = Some((frame_time, frame_idx));
+                }
+                Some((false, frame, frame_time, frame_idx))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opencv::core::{Scalar, CV_8UC3};
+    use std::{path::PathBuf, process::Command};
+    use tempdir::TempDir;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    /// Runs a `SceneChangeIter` over uniformly colored frames, one per second,
+    /// and returns the times and indices of the marked frames.
+    fn scene_changes(gray_values: &[f64], max_unstable: Duration) -> Vec<(Duration, usize)> {
+        let frames = gray_values.iter().enumerate().map(|(idx, &value)| {
+            let frame =
+                Mat::new_rows_cols_with_default(120, 160, CV_8UC3, Scalar::all(value)).unwrap();
+            (frame, secs(idx as u64), idx)
+        });
+        SceneChangeIter::new(frames, 0.98, max_unstable)
+            .filter(|(changed, ..)| *changed)
+            .map(|(_, _, time, idx)| (time, idx))
+            .collect()
+    }
+
+    #[test]
+    fn test_scene_changes() {
+        // The start of the video and the cut at 3s settle one frame later
+        // and are reported at the time of the cut.
+        assert_eq!(
+            scene_changes(&[0.0, 0.0, 0.0, 100.0, 200.0, 200.0, 200.0], secs(10)),
+            vec![(secs(0), 0), (secs(3), 3)]
+        );
+        // A video that never settles is matched whenever it has been unstable for `max_unstable`.
+        assert_eq!(
+            scene_changes(&[0.0, 100.0, 0.0, 100.0, 0.0, 100.0, 0.0, 100.0], secs(3)),
+            vec![(secs(3), 3), (secs(6), 6)]
+        );
+        // A still video is only matched once.
+        assert_eq!(scene_changes(&[50.0; 5], secs(3)), vec![(secs(0), 0)]);
+    }
+
+    #[test]
+    fn test_split_time_ranges() {
+        let interval = secs(5);
+        assert_eq!(
+            split_time_ranges(secs(40), interval, 1),
+            vec![secs(0)..secs(40)]
+        );
+        assert_eq!(
+            split_time_ranges(secs(40), interval, 3),
+            vec![secs(0)..secs(10), secs(10)..secs(25), secs(25)..secs(40)]
+        );
+        // The last sampled frame is at 40s, the last range ends with the video.
+        assert_eq!(
+            split_time_ranges(secs(42), interval, 2),
+            vec![secs(0)..secs(20), secs(20)..secs(42)]
+        );
+        // There are fewer sampled frames than requested ranges.
+        assert_eq!(
+            split_time_ranges(secs(10), interval, 4),
+            vec![secs(0)..secs(5), secs(5)..secs(10)]
+        );
+        assert_eq!(
+            split_time_ranges(secs(0), interval, 4),
+            vec![secs(0)..secs(0)]
+        );
+    }
+
+    /// Generates a variable frame rate video with 10 seconds at 5 fps followed by 10 seconds at 50 fps.
+    /// Its nominal duration `frame count / fps` is far off.
+    fn generate_vfr_video(dir: &Path) -> PathBuf {
+        let path = dir.join("vfr.mp4");
+        let status = Command::new("ffmpeg")
+            .args([
+                "-y",
+                "-loglevel",
+                "error",
+                "-filter_complex",
+                "testsrc=duration=10:rate=5:size=320x240[a];\
+                 testsrc=duration=10:rate=50:size=320x240[b];\
+                 [a][b]concat=n=2:v=1[v]",
+                "-map",
+                "[v]",
+                "-vsync",
+                "vfr",
+                "-c:v",
+                "libx264",
+                "-pix_fmt",
+                "yuv420p",
+            ])
+            .arg(&path)
+            .status();
+        let status = status.expect("ffmpeg must be installed to generate the test video");
+        assert!(
+            status.success(),
+            "ffmpeg failed to generate the test video: {}",
+            status
+        );
+        path
+    }
+
+    // Requires ffmpeg, run with `cargo test -- --ignored`.
+    #[ignore]
+    #[test]
+    fn test_variable_frame_rate() {
+        let temp_dir = TempDir::new("slideo_vfr_test").unwrap();
+        let path = generate_vfr_video(temp_dir.path());
+
+        let interval = secs(3);
+        for &decoding in &[Decoding::Sequential, Decoding::Seek] {
+            let mut video = VideoCaptureIter::open_with_decoding(&path, interval, decoding);
+            let total_time = video.total_time().as_secs_f64();
+            assert!(
+                (total_time - 20.0).abs() < 0.1,
+                "{:?}: total time {}",
+                decoding,
+                total_time
+            );
+
+            let times: Vec<f64> = video.map(|(_, time, _)| time.as_secs_f64()).collect();
+            assert_eq!(times.len(), 7, "{:?}: {:?}", decoding, times);
+            for (idx, time) in times.iter().enumerate() {
+                let expected = (idx * 3) as f64;
+                assert!(
+                    (time - expected).abs() < 0.1,
+                    "{:?}: frame {} at {}, expected {}",
+                    decoding,
+                    idx,
+                    time,
+                    expected
+                );
+            }
+        }
+    }
+}
@@ -1,5 +1,7 @@
 mod progress;
+mod smoothing;
 pub use progress::*;
+pub use smoothing::*;
 
 use std::path::Path;
 use std::time::Duration;
@@ -21,6 +23,12 @@ pub trait VideoMatcher<'i, I: MatchableImage + Clone + 'i> {
         video_path: &Path,
         progress_reporter: ProgressReporter,
     ) -> Box<dyn VideoMatcherTask<I> + 'i>;
+
+    /// Returns groups of consecutive pages of the same deck that only differ by revealed content,
+    /// like the overlays of a beamer frame. Each group has at least two images.
+    fn overlay_groups(&self) -> Vec<Vec<I>> {
+        Vec::new()
+    }
 }
 
 pub trait VideoMatcherTask<I: MatchableImage + Clone> {
@@ -30,6 +38,13 @@ pub trait VideoMatcherTask<I: MatchableImage + Clone> {
 
 pub trait MatchableImage {
     fn get_path(&self) -> &Path;
+
+    /// The deck of this image and its page number in the deck.
+    /// Only adjacent pages of the same deck can form overlay groups,
+    /// images without a position are never part of one.
+    fn deck_position(&self) -> Option<(&str, usize)> {
+        None
+    }
 }
 
 #[derive(Clone)]
@@ -37,6 +52,8 @@ pub struct Matching<I: Clone> {
     pub video_time: Duration,
     pub video_frame_idx: usize,
     pub image: Option<I>,
+    /// A small JPEG encoded image of the matched frame.
+    pub thumbnail: Option<Vec<u8>>,
 }
 
 /*
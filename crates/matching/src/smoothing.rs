@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+
+/// Parameters of the hidden markov model that is used to smooth matchings over time.
+///
+/// The hidden state of a frame is the shown image (or no image at all).
+/// A frame can only show one of its candidate images, so frames without candidates never show an image.
+/// The remaining probability is the probability of jumping to any other image.
+/// It is not divided among the images, so the costs of a jump do not depend on the number of images.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmoothingParams {
+    /// The probability that the next matched frame shows the same image.
+    pub stay_probability: f64,
+    /// The probability of moving to the next page of the same deck.
+    pub next_probability: f64,
+    /// The probability of moving to the previous page of the same deck.
+    pub previous_probability: f64,
+    /// The probability of moving from an image to a frame without any image.
+    pub no_image_probability: f64,
+    /// The minimal score of the "no image" state, which otherwise is `1 - best candidate score`.
+    pub unmatched_score: f64,
+}
+
+impl Default for SmoothingParams {
+    fn default() -> Self {
+        SmoothingParams {
+            stay_probability: 0.8,
+            next_probability: 0.1,
+            previous_probability: 0.03,
+            no_image_probability: 0.02,
+            unmatched_score: 0.01,
+        }
+    }
+}
+
+/// The candidate images of a single frame.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameCandidates {
+    /// Image indices with a score in `(0, 1]`. Higher is better.
+    pub scores: Vec<(usize, f64)>,
+}
+
+/// Returns the index of the image that shows the next page of the same deck for every image.
+///
+/// `positions` contains the deck and the page number of every image, see [`MatchableImage::deck_position`].
+///
+/// [`MatchableImage::deck_position`]: crate::MatchableImage::deck_position
+pub fn next_pages(positions: &[Option<(&str, usize)>]) -> Vec<Option<usize>> {
+    let indices: HashMap<(&str, usize), usize> = positions
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, position)| position.map(|p| (p, idx)))
+        .collect();
+    positions
+        .iter()
+        .map(|position| {
+            position.and_then(|(deck, page_nr)| indices.get(&(deck, page_nr + 1)).copied())
+        })
+        .collect()
+}
+
+/// Finds the most likely sequence of images for the given frames with the Viterbi algorithm.
+///
+/// Images are identified by their index in `0..next_pages.len()`.
+/// `next_pages[i]` is the image that shows the page after image `i`, see [`next_pages`].
+/// Returns `None` for frames that most likely do not show any image.
+pub fn smooth_matchings(
+    frames: &[FrameCandidates],
+    next_pages: &[Option<usize>],
+    params: &SmoothingParams,
+) -> Vec<Option<usize>> {
+    if frames.is_empty() {
+        return Vec::new();
+    }
+    if next_pages.is_empty() {
+        return vec![None; frames.len()];
+    }
+
+    let n = next_pages.len();
+    let mut previous_pages = vec![None; n];
+    for (idx, next) in next_pages.iter().enumerate() {
+        if let Some(next) = *next {
+            previous_pages[next] = Some(idx);
+        }
+    }
+    let no_image = n;
+    let jump_probability = (1.0
+        - params.stay_probability
+        - params.next_probability
+        - params.previous_probability
+        - params.no_image_probability)
+        .max(f64::MIN_POSITIVE);
+
+    let ln_stay = params.stay_probability.ln();
+    let ln_next = params.next_probability.ln();
+    let ln_previous = params.previous_probability.ln();
+    let ln_to_no_image = params.no_image_probability.ln();
+    let ln_jump = jump_probability.ln();
+    let ln_from_no_image = (1.0 - params.stay_probability).ln();
+
+    let emissions = |frame: &FrameCandidates| -> Vec<f64> {
+        let mut result = vec![f64::NEG_INFINITY; n + 1];
+        let mut best_score = 0.0f64;
+        for &(idx, score) in &frame.scores {
+            result[idx] = result[idx].max(score.ln());
+            best_score = best_score.max(score);
+        }
+        result[no_image] = (1.0 - best_score).max(params.unmatched_score).ln();
+        result
+    };
+
+    // `backpointers[t][s]` is the most likely predecessor of state `s` at frame `t`.
+    let mut backpointers = Vec::<Vec<u32>>::with_capacity(frames.len());
+    let mut scores = emissions(&frames[0]);
+    backpointers.push(Vec::new());
+
+    for frame in &frames[1..] {
+        let best_image = (0..n)
+            .max_by(|&a, &b| scores[a].partial_cmp(&scores[b]).unwrap())
+            .unwrap();
+
+        let mut next_scores = vec![f64::NEG_INFINITY; n + 1];
+        let mut pointers = vec![0u32; n + 1];
+        let mut consider = |state: usize, from: usize, score: f64| {
+            if score > next_scores[state] {
+                next_scores[state] = score;
+                pointers[state] = from as u32;
+            }
+        };
+
+        for i in 0..n {
+            consider(i, i, scores[i] + ln_stay);
+            if let Some(previous) = previous_pages[i] {
+                consider(i, previous, scores[previous] + ln_next);
+            }
+            if let Some(next) = next_pages[i] {
+                consider(i, next, scores[next] + ln_previous);
+            }
+            consider(i, best_image, scores[best_image] + ln_jump);
+            consider(i, no_image, scores[no_image] + ln_from_no_image);
+        }
+        consider(no_image, no_image, scores[no_image] + ln_stay);
+        consider(no_image, best_image, scores[best_image] + ln_to_no_image);
+
+        for (score, emission) in next_scores.iter_mut().zip(emissions(frame)) {
+            *score += emission;
+        }
+        scores = next_scores;
+        backpointers.push(pointers);
+    }
+
+    let mut state = (0..=n)
+        .max_by(|&a, &b| scores[a].partial_cmp(&scores[b]).unwrap())
+        .unwrap();
+    let mut result = vec![None; frames.len()];
+    for t in (0..frames.len()).rev() {
+        result[t] = if state == no_image { None } else { Some(state) };
+        if t > 0 {
+            state = backpointers[t][state] as usize;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(scores: &[(usize, f64)]) -> FrameCandidates {
+        FrameCandidates {
+            scores: scores.to_vec(),
+        }
+    }
+
+    /// The next pages of a single deck with the given number of pages.
+    fn deck(page_count: usize) -> Vec<Option<usize>> {
+        (1..page_count).map(Some).chain([None]).collect()
+    }
+
+    #[test]
+    fn test_smooth_matchings() {
+        let params = SmoothingParams::default();
+
+        // A single frame that is matched with an unrelated page is ignored.
+        let frames = vec![
+            frame(&[(3, 0.9)]),
+            frame(&[(3, 0.9)]),
+            frame(&[(17, 0.7), (3, 0.6)]),
+            frame(&[(3, 0.9)]),
+            frame(&[(4, 0.9)]),
+            frame(&[(4, 0.9)]),
+        ];
+        assert_eq!(
+            smooth_matchings(&frames, &deck(20), &params),
+            vec![Some(3), Some(3), Some(3), Some(3), Some(4), Some(4)]
+        );
+
+        // Jumps that are supported by several frames are kept.
+        let frames = vec![
+            frame(&[(3, 0.9)]),
+            frame(&[(17, 0.9)]),
+            frame(&[(17, 0.9)]),
+            frame(&[(17, 0.9)]),
+        ];
+        assert_eq!(
+            smooth_matchings(&frames, &deck(20), &params),
+            vec![Some(3), Some(17), Some(17), Some(17)]
+        );
+
+        // Frames without any slide.
+        let frames = vec![
+            frame(&[(0, 0.9)]),
+            frame(&[(0, 0.9)]),
+            frame(&[]),
+            frame(&[]),
+        ];
+        assert_eq!(
+            smooth_matchings(&frames, &deck(2), &params),
+            vec![Some(0), Some(0), None, None]
+        );
+
+        assert_eq!(smooth_matchings(&[], &deck(2), &params), vec![]);
+        assert_eq!(smooth_matchings(&frames, &[], &params), vec![None; 4]);
+    }
+
+    #[test]
+    fn test_frames_without_candidates_show_no_image() {
+        let params = SmoothingParams::default();
+
+        // A frame that did not match any image well enough is not replaced by the surrounding image.
+        let frames = vec![
+            frame(&[(3, 0.9)]),
+            frame(&[(3, 0.9)]),
+            frame(&[]),
+            frame(&[(3, 0.9)]),
+            frame(&[(3, 0.9)]),
+        ];
+        assert_eq!(
+            smooth_matchings(&frames, &deck(5), &params),
+            vec![Some(3), Some(3), None, Some(3), Some(3)]
+        );
+    }
+
+    #[test]
+    fn test_next_pages() {
+        let positions = vec![
+            Some(("a", 1)),
+            Some(("a", 2)),
+            Some(("b", 3)),
+            Some(("a", 4)),
+            Some(("b", 4)),
+            None,
+        ];
+        assert_eq!(
+            next_pages(&positions),
+            vec![Some(1), None, Some(4), None, None, None]
+        );
+    }
+
+    #[test]
+    fn test_only_pages_of_the_same_deck_are_adjacent() {
+        let params = SmoothingParams::default();
+        let frames = vec![frame(&[(1, 0.9)]), frame(&[(2, 0.6), (5, 0.7)])];
+
+        // Moving to the next page is more likely than jumping to a slightly better matching page.
+        assert_eq!(
+            smooth_matchings(&frames, &deck(6), &params),
+            vec![Some(1), Some(2)]
+        );
+
+        // Image 1 is the last page of a deck and image 2 the first page of another deck.
+        let next_pages = vec![Some(1), None, Some(3), Some(4), Some(5), None];
+        assert_eq!(
+            smooth_matchings(&frames, &next_pages, &params),
+            vec![Some(1), Some(5)]
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use std::{fmt, io, process::ExitStatus};
+
+#[derive(Debug)]
+pub enum Error {
+    /// A file could not be accessed or a tool could not be started.
+    Io(io::Error),
+    /// A poppler tool exited with a failure.
+    ToolFailed {
+        tool: &'static str,
+        status: ExitStatus,
+        stderr: String,
+    },
+    /// The output of a poppler tool could not be understood.
+    InvalidOutput { tool: &'static str, message: String },
+    /// A pdf that is read directly could not be understood.
+    InvalidPdf(String),
+    /// A page could not be rendered.
+    PageFailed { page_nr: u32, source: Box<Error> },
+    /// The operation was aborted through a `CancellationToken`.
+    Cancelled,
+    /// The rasterizer does not support the requested options.
+    Unsupported(&'static str),
+    /// The in-process rasterizer failed.
+    Rasterizer(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "{}", err),
+            Error::ToolFailed {
+                tool,
+                status,
+                stderr,
+            } => write!(f, "{} failed ({}): {}", tool, status, stderr.trim()),
+            Error::InvalidOutput { tool, message } => {
+                write!(f, "Unexpected output of {}: {}", tool, message)
+            }
+            Error::InvalidPdf(message) => write!(f, "Invalid pdf: {}", message),
+            Error::PageFailed { page_nr, source } => {
+                write!(f, "Could not render page {}: {}", page_nr, source)
+            }
+            Error::Cancelled => write!(f, "Cancelled"),
+            Error::Unsupported(message) => write!(f, "Unsupported: {}", message),
+            Error::Rasterizer(message) => write!(f, "Rendering failed: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::PageFailed { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
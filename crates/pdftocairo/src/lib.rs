@@ -1,5 +1,22 @@
+mod error;
+mod page_labels;
+mod page_selection;
 mod pdf_info;
+mod pdfimages;
+#[cfg(feature = "pdfium")]
+mod pdfium;
 mod pdftocairo;
+mod pdftotext;
+mod rasterizer;
+mod tool;
 
+pub use error::*;
+pub use page_labels::*;
+pub use page_selection::*;
 pub use pdf_info::*;
+pub use pdfimages::*;
+#[cfg(feature = "pdfium")]
+pub use pdfium::*;
 pub use pdftocairo::*;
+pub use pdftotext::*;
+pub use rasterizer::*;
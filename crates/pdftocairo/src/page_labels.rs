@@ -0,0 +1,762 @@
+use crate::Error;
+use flate2::read::ZlibDecoder;
+use std::{collections::HashMap, fs::read, io::Read, path::Path};
+
+/// Reads the page labels (like `iv` or `A-1`) from the `/PageLabels` tree of the document catalog.
+/// The first entry is the label of the first page.
+///
+/// Poppler does not print page labels, so the pdf is read directly.
+/// Returns `None` if the pdf does not define page labels.
+/// Encrypted pdfs are not supported.
+pub fn pdf_page_labels(pdf: &Path) -> Result<Option<Vec<String>>, Error> {
+    parse_page_labels(&read(pdf)?)
+}
+
+fn parse_page_labels(data: &[u8]) -> Result<Option<Vec<String>>, Error> {
+    let document = Document::parse(data);
+    if document.trailer.contains_key("Encrypt") {
+        return Err(Error::Unsupported(
+            "Page labels of encrypted pdfs cannot be read",
+        ));
+    }
+
+    let catalog = document
+        .trailer
+        .get("Root")
+        .map(|root| document.resolve(root))
+        .and_then(Object::as_dict)
+        .ok_or_else(|| invalid_pdf("The document catalog is missing"))?;
+    let page_count = catalog
+        .get("Pages")
+        .map(|pages| document.resolve(pages))
+        .and_then(Object::as_dict)
+        .and_then(|pages| pages.get("Count"))
+        .and_then(|count| document.resolve(count).as_number())
+        .ok_or_else(|| invalid_pdf("The page count is missing"))? as u32;
+    let tree = match catalog.get("PageLabels") {
+        Some(tree) => document.resolve(tree),
+        None => return Ok(None),
+    };
+
+    let mut ranges = Vec::<(u32, LabelStyle)>::new();
+    document.collect_number_tree(tree, 0, &mut ranges);
+    ranges.sort_by_key(|(start, _)| *start);
+
+    let labels = (0..page_count)
+        .map(
+            |page_idx| match ranges.iter().rev().find(|(start, _)| *start <= page_idx) {
+                Some((start, style)) => style.label(page_idx - start),
+                // The first range should start at the first page.
+                None => (page_idx + 1).to_string(),
+            },
+        )
+        .collect();
+    Ok(Some(labels))
+}
+
+fn invalid_pdf(message: impl Into<String>) -> Error {
+    Error::InvalidPdf(message.into())
+}
+
+/// A page label range of the `/PageLabels` number tree.
+#[derive(Debug)]
+struct LabelStyle {
+    /// `D`, `R`, `r`, `A` or `a`. Without numbering style, the label is just the prefix.
+    numbering: Option<String>,
+    prefix: String,
+    /// The number of the first page of the range.
+    start: u32,
+}
+
+impl LabelStyle {
+    fn of(dict: &HashMap<String, Object>) -> LabelStyle {
+        LabelStyle {
+            numbering: dict
+                .get("S")
+                .and_then(Object::as_name)
+                .map(|s| s.to_owned()),
+            prefix: dict
+                .get("P")
+                .and_then(Object::as_string)
+                .map(decode_text_string)
+                .unwrap_or_default(),
+            start: dict
+                .get("St")
+                .and_then(Object::as_number)
+                .map_or(1, |st| st.max(1.0) as u32),
+        }
+    }
+
+    /// `offset` is the index of the page within the range.
+    fn label(&self, offset: u32) -> String {
+        let nr = self.start + offset;
+        let numeral = match self.numbering.as_deref() {
+            Some("D") => nr.to_string(),
+            Some("R") => to_roman(nr),
+            Some("r") => to_roman(nr).to_lowercase(),
+            Some("A") => to_letters(nr),
+            Some("a") => to_letters(nr).to_lowercase(),
+            _ => String::new(),
+        };
+        format!("{}{}", self.prefix, numeral)
+    }
+}
+
+fn to_roman(mut nr: u32) -> String {
+    const NUMERALS: [(u32, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let mut result = String::new();
+    for (value, numeral) in NUMERALS.iter() {
+        while nr >= *value {
+            result.push_str(numeral);
+            nr -= value;
+        }
+    }
+    result
+}
+
+/// `A` to `Z`, then `AA` to `ZZ`, then `AAA` and so on.
+fn to_letters(nr: u32) -> String {
+    let letter = (b'A' + ((nr - 1) % 26) as u8) as char;
+    std::iter::repeat_n(letter, ((nr - 1) / 26 + 1) as usize).collect()
+}
+
+/// Text strings are either UTF-16BE with a byte order mark or PDFDocEncoding,
+/// which is treated as Latin-1.
+fn decode_text_string(bytes: &[u8]) -> String {
+    match bytes.strip_prefix(b"\xFE\xFF") {
+        Some(utf16) => {
+            let units: Vec<u16> = utf16
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        None => bytes.iter().map(|&b| b as char).collect(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Object {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Name(String),
+    String(Vec<u8>),
+    Array(Vec<Object>),
+    Dict(HashMap<String, Object>),
+    Ref(u32),
+    Stream(HashMap<String, Object>, Vec<u8>),
+}
+
+impl Object {
+    fn as_dict(&self) -> Option<&HashMap<String, Object>> {
+        match self {
+            Object::Dict(dict) | Object::Stream(dict, _) => Some(dict),
+            _ => None,
+        }
+    }
+
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            Object::Number(nr) => Some(*nr),
+            _ => None,
+        }
+    }
+
+    fn as_name(&self) -> Option<&str> {
+        match self {
+            Object::Name(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    fn as_string(&self) -> Option<&[u8]> {
+        match self {
+            Object::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// The objects of a pdf, found by scanning the file instead of reading the cross-reference table.
+/// This also reads pdfs with a broken cross-reference table.
+/// Later definitions of an object replace earlier ones, like incremental updates do.
+struct Document {
+    objects: HashMap<u32, Object>,
+    trailer: HashMap<String, Object>,
+}
+
+/// Number trees are not nested deeper than this, which also stops reference cycles.
+const MAX_TREE_DEPTH: usize = 32;
+
+impl Document {
+    fn parse(data: &[u8]) -> Document {
+        let mut objects = HashMap::new();
+        let mut trailer = HashMap::new();
+        let mut object_streams = Vec::new();
+
+        let mut pos = 0;
+        while let Some(offset) = find(data, b"obj", pos) {
+            pos = offset + 3;
+            let object_nr = match object_number_before(data, offset) {
+                Some(object_nr) if is_delimiter(data.get(pos)) => object_nr,
+                _ => continue,
+            };
+            let mut parser = Parser { data, pos };
+            let object = match parser.parse_indirect_object() {
+                Some(object) => object,
+                None => continue,
+            };
+            pos = parser.pos;
+            if let Object::Stream(dict, _) = &object {
+                match dict.get("Type").and_then(Object::as_name) {
+                    Some("ObjStm") => object_streams.push(object_nr),
+                    // Cross-reference streams replace the trailer.
+                    Some("XRef") => trailer = dict.clone(),
+                    _ => {}
+                }
+            }
+            objects.insert(object_nr, object);
+        }
+
+        let mut pos = 0;
+        while let Some(offset) = find(data, b"trailer", pos) {
+            pos = offset + 7;
+            let mut parser = Parser { data, pos };
+            if let Some(Object::Dict(dict)) = parser.parse_object() {
+                // Incremental updates keep the entries of the previous trailer.
+                trailer.extend(dict);
+            }
+        }
+
+        let mut document = Document { objects, trailer };
+        for object_nr in object_streams {
+            for (nr, object) in document.read_object_stream(object_nr) {
+                document.objects.entry(nr).or_insert(object);
+            }
+        }
+        document
+    }
+
+    /// Returns the objects that are stored in the given object stream.
+    fn read_object_stream(&self, object_nr: u32) -> Vec<(u32, Object)> {
+        let (dict, data) = match self.objects.get(&object_nr) {
+            Some(Object::Stream(dict, data)) => (dict, data),
+            _ => return Vec::new(),
+        };
+        let data = match decode_stream(dict, data) {
+            Some(data) => data,
+            None => return Vec::new(),
+        };
+        let count = dict.get("N").and_then(Object::as_number).unwrap_or(0.0) as usize;
+        let first = dict.get("First").and_then(Object::as_number).unwrap_or(0.0) as usize;
+
+        // The stream starts with pairs of object numbers and offsets relative to `first`.
+        let mut header = Parser {
+            data: &data,
+            pos: 0,
+        };
+        let mut result = Vec::new();
+        for _ in 0..count {
+            let (nr, offset) = match (header.parse_object(), header.parse_object()) {
+                (Some(Object::Number(nr)), Some(Object::Number(offset))) => (nr, offset),
+                _ => break,
+            };
+            let mut parser = Parser {
+                data: &data,
+                pos: first + offset as usize,
+            };
+            if let Some(object) = parser.parse_object() {
+                result.push((nr as u32, object));
+            }
+        }
+        result
+    }
+
+    fn resolve<'a>(&'a self, object: &'a Object) -> &'a Object {
+        let mut object = object;
+        // Limits chains of references to references.
+        for _ in 0..MAX_TREE_DEPTH {
+            match object {
+                Object::Ref(nr) => object = self.objects.get(nr).unwrap_or(&Object::Null),
+                _ => return object,
+            }
+        }
+        &Object::Null
+    }
+
+    /// Collects the entries of the number tree with the given root.
+    fn collect_number_tree(
+        &self,
+        node: &Object,
+        depth: usize,
+        result: &mut Vec<(u32, LabelStyle)>,
+    ) {
+        let node = match self.resolve(node).as_dict() {
+            Some(node) if depth < MAX_TREE_DEPTH => node,
+            _ => return,
+        };
+        if let Some(Object::Array(nums)) = node.get("Nums").map(|n| self.resolve(n)) {
+            for pair in nums.chunks_exact(2) {
+                let start = self.resolve(&pair[0]).as_number();
+                let style = self.resolve(&pair[1]).as_dict();
+                if let (Some(start), Some(style)) = (start, style) {
+                    result.push((start as u32, LabelStyle::of(style)));
+                }
+            }
+        }
+        if let Some(Object::Array(kids)) = node.get("Kids").map(|k| self.resolve(k)) {
+            for kid in kids {
+                self.collect_number_tree(kid, depth + 1, result);
+            }
+        }
+    }
+}
+
+/// Decodes unfiltered and flate compressed streams.
+fn decode_stream(dict: &HashMap<String, Object>, data: &[u8]) -> Option<Vec<u8>> {
+    let filter = match dict.get("Filter") {
+        Some(Object::Array(filters)) if filters.len() == 1 => filters[0].as_name(),
+        Some(Object::Array(filters)) if filters.is_empty() => None,
+        Some(filter) => Some(filter.as_name()?),
+        None => None,
+    };
+    match filter {
+        None => Some(data.to_vec()),
+        Some("FlateDecode") => {
+            let mut decoded = Vec::new();
+            ZlibDecoder::new(data).read_to_end(&mut decoded).ok()?;
+            Some(decoded)
+        }
+        Some(_) => None,
+    }
+}
+
+fn find(data: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    data.get(from..)?
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|idx| from + idx)
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\r' | b'\n' | b'\x0c' | b'\0')
+}
+
+/// The end of the data counts as delimiter.
+fn is_delimiter(b: Option<&u8>) -> bool {
+    b.is_none_or(|&b| is_whitespace(b) || b"()<>[]{}/%".contains(&b))
+}
+
+/// Parses `12 0` of `12 0 obj`, where `obj_offset` is the offset of `obj`.
+fn object_number_before(data: &[u8], obj_offset: usize) -> Option<u32> {
+    let mut pos = obj_offset;
+    let mut numbers = [0u32; 2];
+    for number in numbers.iter_mut().rev() {
+        let end = pos
+            - data[..pos]
+                .iter()
+                .rev()
+                .take_while(|&&b| is_whitespace(b))
+                .count();
+        let start = end
+            - data[..end]
+                .iter()
+                .rev()
+                .take_while(|b| b.is_ascii_digit())
+                .count();
+        if start == end || end == pos {
+            return None;
+        }
+        *number = std::str::from_utf8(&data[start..end]).ok()?.parse().ok()?;
+        pos = start;
+    }
+    if !is_delimiter(pos.checked_sub(1).map(|p| &data[p])) {
+        return None;
+    }
+    Some(numbers[0])
+}
+
+struct Parser<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b) = self.peek() {
+            if is_whitespace(b) {
+                self.pos += 1;
+            } else if b == b'%' {
+                // Comments end with the line.
+                while !matches!(self.peek(), None | Some(b'\r') | Some(b'\n')) {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn starts_with(&self, token: &[u8]) -> bool {
+        self.data[self.pos..].starts_with(token)
+    }
+
+    /// Parses the object after `obj` and the stream data that follows it.
+    fn parse_indirect_object(&mut self) -> Option<Object> {
+        let object = self.parse_object()?;
+        self.skip_whitespace();
+        let dict = match object {
+            Object::Dict(dict) if self.starts_with(b"stream") => dict,
+            object => return Some(object),
+        };
+
+        self.pos += b"stream".len();
+        // The keyword is followed by CRLF or LF.
+        if self.starts_with(b"\r\n") {
+            self.pos += 2;
+        } else if self.starts_with(b"\n") {
+            self.pos += 1;
+        }
+        let start = self.pos;
+        let length = dict.get("Length").and_then(Object::as_number);
+        let end = match length.map(|l| start + l as usize) {
+            // Indirect lengths are not resolved, the end is searched instead.
+            Some(end) if self.is_stream_end(end) => end,
+            _ => {
+                let end = find(self.data, b"endstream", start)?;
+                let eol = self.data[start..end]
+                    .iter()
+                    .rev()
+                    .take_while(|&&b| b == b'\r' || b == b'\n')
+                    .count();
+                end - eol
+            }
+        };
+        self.pos = end;
+        Some(Object::Stream(dict, self.data[start..end].to_vec()))
+    }
+
+    fn is_stream_end(&self, end: usize) -> bool {
+        let mut parser = Parser {
+            data: self.data,
+            pos: end,
+        };
+        parser.skip_whitespace();
+        end <= self.data.len() && parser.starts_with(b"endstream")
+    }
+
+    fn parse_object(&mut self) -> Option<Object> {
+        self.skip_whitespace();
+        let b = self.peek()?;
+        match b {
+            b'<' if self.starts_with(b"<<") => {
+                self.pos += 2;
+                let mut dict = HashMap::new();
+                loop {
+                    self.skip_whitespace();
+                    if self.starts_with(b">>") {
+                        self.pos += 2;
+                        return Some(Object::Dict(dict));
+                    }
+                    let key = match self.parse_object()? {
+                        Object::Name(key) => key,
+                        _ => return None,
+                    };
+                    let value = self.parse_object()?;
+                    dict.insert(key, value);
+                }
+            }
+            b'<' => {
+                self.pos += 1;
+                let end = find(self.data, b">", self.pos)?;
+                let digits: Vec<u8> = self.data[self.pos..end]
+                    .iter()
+                    .filter(|b| b.is_ascii_hexdigit())
+                    .copied()
+                    .collect();
+                self.pos = end + 1;
+                // A missing last digit is zero.
+                let bytes = digits
+                    .chunks(2)
+                    .map(|pair| {
+                        let hex = std::str::from_utf8(pair).unwrap();
+                        u8::from_str_radix(&format!("{:0<2}", hex), 16).unwrap()
+                    })
+                    .collect();
+                Some(Object::String(bytes))
+            }
+            b'[' => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                loop {
+                    self.skip_whitespace();
+                    if self.peek()? == b']' {
+                        self.pos += 1;
+                        return Some(Object::Array(items));
+                    }
+                    items.push(self.parse_object()?);
+                }
+            }
+            b'(' => self.parse_literal_string(),
+            b'/' => {
+                self.pos += 1;
+                let start = self.pos;
+                while !is_delimiter(self.data.get(self.pos)) {
+                    self.pos += 1;
+                }
+                Some(Object::Name(decode_name(&self.data[start..self.pos])))
+            }
+            b'0'..=b'9' | b'+' | b'-' | b'.' => {
+                let number = self.parse_number()?;
+                // `12 0 R` is a reference to object 12.
+                let mut reference = Parser {
+                    data: self.data,
+                    pos: self.pos,
+                };
+                reference.skip_whitespace();
+                if reference.peek().is_some_and(|b| b.is_ascii_digit()) {
+                    let generation = reference.parse_number();
+                    reference.skip_whitespace();
+                    if generation.is_some()
+                        && reference.starts_with(b"R")
+                        && is_delimiter(reference.data.get(reference.pos + 1))
+                    {
+                        self.pos = reference.pos + 1;
+                        return Some(Object::Ref(number as u32));
+                    }
+                }
+                Some(Object::Number(number))
+            }
+            _ => {
+                let start = self.pos;
+                while !is_delimiter(self.data.get(self.pos)) {
+                    self.pos += 1;
+                }
+                match &self.data[start..self.pos] {
+                    b"true" => Some(Object::Bool(true)),
+                    b"false" => Some(Object::Bool(false)),
+                    b"null" => Some(Object::Null),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Option<f64> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|b| b.is_ascii_digit() || b"+-.".contains(&b))
+        {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.data[start..self.pos])
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    fn parse_literal_string(&mut self) -> Option<Object> {
+        // Skips the opening parenthesis.
+        self.pos += 1;
+        let mut result = Vec::new();
+        let mut depth = 0;
+        loop {
+            let b = self.peek()?;
+            self.pos += 1;
+            match b {
+                b'(' => {
+                    depth += 1;
+                    result.push(b);
+                }
+                b')' if depth == 0 => return Some(Object::String(result)),
+                b')' => {
+                    depth -= 1;
+                    result.push(b);
+                }
+                b'\\' => {
+                    let escaped = self.peek()?;
+                    self.pos += 1;
+                    match escaped {
+                        b'n' => result.push(b'\n'),
+                        b'r' => result.push(b'\r'),
+                        b't' => result.push(b'\t'),
+                        b'b' => result.push(b'\x08'),
+                        b'f' => result.push(b'\x0c'),
+                        b'0'..=b'7' => {
+                            let mut value = (escaped - b'0') as u32;
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(d @ b'0'..=b'7') => {
+                                        value = value * 8 + (d - b'0') as u32;
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            result.push(value as u8);
+                        }
+                        // A backslash at the end of a line continues the string on the next line.
+                        b'\r' => {
+                            if self.peek() == Some(b'\n') {
+                                self.pos += 1;
+                            }
+                        }
+                        b'\n' => {}
+                        other => result.push(other),
+                    }
+                }
+                _ => result.push(b),
+            }
+        }
+    }
+}
+
+/// Names can contain characters as `#xx`.
+fn decode_name(bytes: &[u8]) -> String {
+    let mut result = Vec::new();
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let hex = bytes
+            .get(idx + 1..idx + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match hex {
+            Some(b) if bytes[idx] == b'#' => {
+                result.push(b);
+                idx += 3;
+            }
+            _ => {
+                result.push(bytes[idx]);
+                idx += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&result).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::ZlibEncoder, Compression};
+    use std::io::Write;
+
+    /// A pdf with the given objects and without a valid cross-reference table.
+    fn pdf(objects: &[&str], trailer: &str) -> Vec<u8> {
+        let mut data = b"%PDF-1.7\n".to_vec();
+        for (idx, object) in objects.iter().enumerate() {
+            data.extend(format!("{} 0 obj\n{}\nendobj\n", idx + 1, object).as_bytes());
+        }
+        data.extend(format!("trailer\n{}\n%%EOF\n", trailer).as_bytes());
+        data
+    }
+
+    #[test]
+    fn test_page_labels() {
+        let data = pdf(
+            &[
+                "<< /Type /Catalog /Pages 2 0 R /PageLabels 3 0 R >>",
+                "<< /Type /Pages /Kids [] /Count 7 >>",
+                "<< /Nums [0 << /S /r >> 2 << /S /D >> 5 4 0 R] >>",
+                "<< /S /D /P (A\\055) /St 8 >>",
+            ],
+            "<< /Root 1 0 R /Size 5 >>",
+        );
+        assert_eq!(
+            parse_page_labels(&data).unwrap(),
+            Some(
+                ["i", "ii", "1", "2", "3", "A-8", "A-9"]
+                    .iter()
+                    .map(|l| l.to_string())
+                    .collect()
+            )
+        );
+    }
+
+    #[test]
+    fn test_page_labels_in_object_stream() {
+        let objects = "<< /Type /Catalog /Pages 3 0 R /PageLabels << /Kids [4 0 R] >> >>\n\
+                       << /Type /Pages /Kids [] /Count 3 >>\n\
+                       << /Nums [0 << /P <FEFF00DC> >> 1 << /S /A /St 27 >>] >>";
+        let offsets = objects
+            .lines()
+            .scan(0, |offset, line| {
+                let start = *offset;
+                *offset += line.len() + 1;
+                Some(start)
+            })
+            .collect::<Vec<_>>();
+        let header = format!("2 {} 3 {} 4 {}\n", offsets[0], offsets[1], offsets[2]);
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(format!("{}{}", header, objects).as_bytes())
+            .unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut data = b"%PDF-1.7\n1 0 obj\n".to_vec();
+        data.extend(
+            format!(
+                "<< /Type /ObjStm /N 3 /First {} /Filter /FlateDecode /Length {} >>\nstream\n",
+                header.len(),
+                compressed.len()
+            )
+            .as_bytes(),
+        );
+        data.extend(&compressed);
+        data.extend(b"\nendstream\nendobj\n");
+        data.extend(
+            b"5 0 obj\n<< /Type /XRef /Root 2 0 R /Size 6 /Length 0 >>\nstream\n\nendstream\nendobj\n",
+        );
+
+        assert_eq!(
+            parse_page_labels(&data).unwrap(),
+            Some(vec![
+                "\u{DC}".to_string(),
+                "AA".to_string(),
+                "BB".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_without_page_labels() {
+        let data = pdf(
+            &[
+                "<< /Type /Catalog /Pages 2 0 R >>",
+                "<< /Type /Pages /Kids [] /Count 1 >>",
+            ],
+            "<< /Root 1 0 R >>",
+        );
+        assert_eq!(parse_page_labels(&data).unwrap(), None);
+        assert!(parse_page_labels(b"%PDF-1.7\n").is_err());
+    }
+
+    #[test]
+    fn test_numerals() {
+        assert_eq!(to_roman(1994), "MCMXCIV");
+        assert_eq!(to_roman(4), "IV");
+        assert_eq!(to_letters(1), "A");
+        assert_eq!(to_letters(26), "Z");
+        assert_eq!(to_letters(27), "AA");
+        assert_eq!(to_letters(53), "AAA");
+    }
+}
@@ -0,0 +1,126 @@
+use std::{fmt, str::FromStr};
+
+/// A set of pages like `1-10,15,20-`.
+/// Page numbers are one based, ranges are inclusive and may be open-ended.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PageSelection {
+    ranges: Vec<PageRange>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+struct PageRange {
+    first: u32,
+    /// `None` for ranges that extend to the last page.
+    last: Option<u32>,
+}
+
+impl PageSelection {
+    pub fn contains(&self, page_nr: u32) -> bool {
+        self.ranges
+            .iter()
+            .any(|r| r.first <= page_nr && r.last.is_none_or(|l| page_nr <= l))
+    }
+
+    /// Returns the selected pages of a document with `page_count` pages in ascending order.
+    pub fn pages(&self, page_count: u32) -> Vec<u32> {
+        (1..=page_count).filter(|p| self.contains(*p)).collect()
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParsePageSelectionError(String);
+
+impl fmt::Display for ParsePageSelectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid page selection: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParsePageSelectionError {}
+
+impl FromStr for PageSelection {
+    type Err = ParsePageSelectionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_page_nr = |s: &str| -> Result<u32, ParsePageSelectionError> {
+            match s.trim().parse::<u32>() {
+                Ok(page_nr) if page_nr >= 1 => Ok(page_nr),
+                _ => Err(ParsePageSelectionError(format!(
+                    "'{}' is not a page number",
+                    s.trim()
+                ))),
+            }
+        };
+
+        let mut ranges = Vec::<PageRange>::new();
+        for part in s.split(',') {
+            let range = match part.split_once('-') {
+                None => {
+                    let page_nr = parse_page_nr(part)?;
+                    PageRange {
+                        first: page_nr,
+                        last: Some(page_nr),
+                    }
+                }
+                Some((first, last)) => PageRange {
+                    first: if first.trim().is_empty() {
+                        1
+                    } else {
+                        parse_page_nr(first)?
+                    },
+                    last: if last.trim().is_empty() {
+                        None
+                    } else {
+                        Some(parse_page_nr(last)?)
+                    },
+                },
+            };
+            if range.last.is_some_and(|l| l < range.first) {
+                return Err(ParsePageSelectionError(format!(
+                    "'{}' is an empty range",
+                    part.trim()
+                )));
+            }
+            ranges.push(range);
+        }
+
+        Ok(PageSelection { ranges })
+    }
+}
+
+impl fmt::Display for PageSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, range) in self.ranges.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ",")?;
+            }
+            match range.last {
+                Some(last) if last == range.first => write!(f, "{}", last)?,
+                Some(last) => write!(f, "{}-{}", range.first, last)?,
+                None => write!(f, "{}-", range.first)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_page_selection() {
+        let selection: PageSelection = "1-3, 7,10-".parse().unwrap();
+        assert_eq!(selection.pages(12), vec![1, 2, 3, 7, 10, 11, 12]);
+        assert_eq!(selection.to_string(), "1-3,7,10-");
+
+        let selection: PageSelection = "-2,2".parse().unwrap();
+        assert_eq!(selection.pages(5), vec![1, 2]);
+
+        assert!("".parse::<PageSelection>().is_err());
+        assert!("0".parse::<PageSelection>().is_err());
+        assert!("5-3".parse::<PageSelection>().is_err());
+        assert!("1,,2".parse::<PageSelection>().is_err());
+        assert!("a-b".parse::<PageSelection>().is_err());
+    }
+}
@@ -1,8 +1,32 @@
-use std::{collections::HashMap, io, path::Path, process::Command};
+use crate::{
+    tool::{invalid_output, run_tool},
+    Error,
+};
+use std::{collections::HashMap, path::Path, process::Command};
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+/// A size in PDF points (1/72 inch).
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct PageSize {
+    pub width: f64,
+    pub height: f64,
+}
+
+/// A rectangle in PDF points.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct PdfBox {
+    pub x1: f64,
+    pub y1: f64,
+    pub x2: f64,
+    pub y2: f64,
+}
+
+/// The document properties printed by `pdfinfo`.
+/// `pdfinfo` does not print page labels, see [`pdf_page_labels`](crate::pdf_page_labels) for them.
+#[derive(Debug, PartialEq, Clone)]
 pub struct PdfInfo {
     page_count: u32,
+    page_size: Option<PageSize>,
+    properties: HashMap<String, String>,
 }
 
 impl PdfInfo {
@@ -10,34 +34,126 @@ impl PdfInfo {
     pub fn page_count(&self) -> u32 {
         self.page_count
     }
+
+    /// Returns the size of the first page.
+    pub fn page_size(&self) -> Option<PageSize> {
+        self.page_size
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.property("Title")
+    }
+
+    pub fn subject(&self) -> Option<&str> {
+        self.property("Subject")
+    }
+
+    pub fn keywords(&self) -> Option<&str> {
+        self.property("Keywords")
+    }
+
+    pub fn author(&self) -> Option<&str> {
+        self.property("Author")
+    }
+
+    pub fn creator(&self) -> Option<&str> {
+        self.property("Creator")
+    }
+
+    pub fn producer(&self) -> Option<&str> {
+        self.property("Producer")
+    }
+
+    /// As formatted by `pdfinfo`.
+    pub fn creation_date(&self) -> Option<&str> {
+        self.property("CreationDate")
+    }
+
+    /// As formatted by `pdfinfo`.
+    pub fn modification_date(&self) -> Option<&str> {
+        self.property("ModDate")
+    }
+
+    pub fn pdf_version(&self) -> Option<&str> {
+        self.property("PDF version")
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.property("Encrypted")
+            .is_some_and(|v| v.starts_with("yes"))
+    }
+
+    /// Returns any property printed by `pdfinfo`, e.g. `Producer`.
+    /// Empty properties are treated as missing.
+    pub fn property(&self, name: &str) -> Option<&str> {
+        self.properties
+            .get(name)
+            .map(|v| v as &str)
+            .filter(|v| !v.is_empty())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct PageInfo {
+    /// Starts with 1.
+    pub page_nr: u32,
+    pub size: PageSize,
+    /// In degrees.
+    pub rotation: u32,
+    pub media_box: Option<PdfBox>,
+    pub crop_box: Option<PdfBox>,
+    pub bleed_box: Option<PdfBox>,
+    pub trim_box: Option<PdfBox>,
+    pub art_box: Option<PdfBox>,
 }
 
 /// Invokes the `pdfinfo` tool and parses the result.
-pub fn pdf_info(pdf: &Path) -> Result<PdfInfo, io::Error> {
-    let result = Command::new(&"pdfinfo").arg(&pdf).output()?;
+pub fn pdf_info(pdf: &Path) -> Result<PdfInfo, Error> {
+    let stdout = run_tool("pdfinfo", Command::new("pdfinfo").arg(pdf))?;
+    let properties = parse_pdf_info(&stdout);
 
-    if !result.status.success() {
-        println!("unsuccess");
-    }
+    let page_count: u32 = properties
+        .get("Pages")
+        .and_then(|p| p.parse().ok())
+        .ok_or_else(|| invalid_output("pdfinfo", "Page count is missing"))?;
+    let page_size = properties
+        .get("Page size")
+        .map(|s| parse_page_size(s))
+        .transpose()?;
 
-    let stdout = String::from_utf8_lossy(&result.stdout);
-    let info = parse_pdf_info(&stdout);
-    let page_count: u32 = info["Pages"].parse().unwrap();
+    Ok(PdfInfo {
+        page_count,
+        page_size,
+        properties,
+    })
+}
 
-    Ok(PdfInfo { page_count })
+/// Invokes the `pdfinfo` tool to get the sizes and boxes of the given pages.
+/// Page numbers are one based and inclusive.
+pub fn pdf_page_infos(pdf: &Path, first_page: u32, last_page: u32) -> Result<Vec<PageInfo>, Error> {
+    let stdout = run_tool(
+        "pdfinfo",
+        Command::new("pdfinfo")
+            .args(["-f", &first_page.to_string()])
+            .args(["-l", &last_page.to_string()])
+            .arg("-box")
+            .arg(pdf),
+    )?;
+    parse_page_infos(&stdout)
 }
 
 fn parse_pdf_info(lines: &str) -> HashMap<String, String> {
     let mut map = HashMap::<String, String>::new();
 
     for line in lines.split('\n') {
-        if line.trim().len() == 0 {
+        if line.trim().is_empty() {
             continue;
         }
 
         let parts: Vec<&str> = line.splitn(2, ':').collect();
         if parts.len() != 2 {
-            panic!("Got invalid line '{}'", line);
+            // Multi-line values (e.g. titles with line breaks) are not supported.
+            continue;
         }
         map.insert(parts[0].trim().to_owned(), parts[1].trim().to_owned());
     }
@@ -45,8 +161,103 @@ fn parse_pdf_info(lines: &str) -> HashMap<String, String> {
     map
 }
 
+/// Parses `pdfinfo` page lines like `Page    1 MediaBox:     0.00     0.00   720.00   540.00`.
+fn parse_page_infos(lines: &str) -> Result<Vec<PageInfo>, Error> {
+    let mut pages = Vec::<PageInfo>::new();
+
+    for (key, value) in parse_pdf_info(lines) {
+        let mut key_parts = key.split_whitespace();
+        if key_parts.next() != Some("Page") {
+            continue;
+        }
+        let page_nr: u32 = match key_parts.next().and_then(|n| n.parse().ok()) {
+            Some(page_nr) => page_nr,
+            // E.g. "Page size" of the document
+            None => continue,
+        };
+        let property = key_parts.collect::<Vec<_>>().join(" ");
+
+        let page = match pages.iter_mut().position(|p| p.page_nr == page_nr) {
+            Some(idx) => &mut pages[idx],
+            None => {
+                pages.push(PageInfo {
+                    page_nr,
+                    size: PageSize {
+                        width: 0.0,
+                        height: 0.0,
+                    },
+                    rotation: 0,
+                    media_box: None,
+                    crop_box: None,
+                    bleed_box: None,
+                    trim_box: None,
+                    art_box: None,
+                });
+                pages.last_mut().unwrap()
+            }
+        };
+
+        match property.as_str() {
+            "size" => page.size = parse_page_size(&value)?,
+            "rot" => {
+                page.rotation = value.parse().map_err(|_| {
+                    invalid_output("pdfinfo", format!("Invalid rotation '{}'", value))
+                })?
+            }
+            "MediaBox" => page.media_box = Some(parse_box(&value)?),
+            "CropBox" => page.crop_box = Some(parse_box(&value)?),
+            "BleedBox" => page.bleed_box = Some(parse_box(&value)?),
+            "TrimBox" => page.trim_box = Some(parse_box(&value)?),
+            "ArtBox" => page.art_box = Some(parse_box(&value)?),
+            _ => {}
+        }
+    }
+
+    pages.sort_by_key(|p| p.page_nr);
+    Ok(pages)
+}
+
+/// Parses sizes like `720 x 540 pts (A4)`.
+fn parse_page_size(value: &str) -> Result<PageSize, Error> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    match parts.as_slice() {
+        [width, "x", height, ..] => match (width.parse(), height.parse()) {
+            (Ok(width), Ok(height)) => Ok(PageSize { width, height }),
+            _ => Err(invalid_output(
+                "pdfinfo",
+                format!("Invalid page size '{}'", value),
+            )),
+        },
+        _ => Err(invalid_output(
+            "pdfinfo",
+            format!("Invalid page size '{}'", value),
+        )),
+    }
+}
+
+fn parse_box(value: &str) -> Result<PdfBox, Error> {
+    let coords = value
+        .split_whitespace()
+        .map(|c| c.parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid_output("pdfinfo", format!("Invalid box '{}'", value)))?;
+    match coords.as_slice() {
+        [x1, y1, x2, y2] => Ok(PdfBox {
+            x1: *x1,
+            y1: *y1,
+            x2: *x2,
+            y2: *y2,
+        }),
+        _ => Err(invalid_output(
+            "pdfinfo",
+            format!("Invalid box '{}'", value),
+        )),
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
     use std::path::PathBuf;
 
     #[test]
@@ -55,4 +266,37 @@ mod tests {
         let info = super::pdf_info(&path).unwrap();
         assert_eq!(info.page_count(), 42);
     }
+
+    #[test]
+    fn test_parse_page_infos() {
+        let output = "
+Page    1 size:     720 x 540 pts
+Page    1 rot:      90
+Page    1 MediaBox:     0.00     0.00   720.00   540.00
+Page    2 size:     595.276 x 841.89 pts (A4)
+Page    2 rot:      0
+Page    2 CropBox:      10.00     20.00   585.28   821.89
+";
+        let pages = parse_page_infos(output).unwrap();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].page_nr, 1);
+        assert_eq!(pages[0].rotation, 90);
+        assert_eq!(
+            pages[0].media_box,
+            Some(PdfBox {
+                x1: 0.0,
+                y1: 0.0,
+                x2: 720.0,
+                y2: 540.0
+            })
+        );
+        assert_eq!(
+            pages[1].size,
+            PageSize {
+                width: 595.276,
+                height: 841.89
+            }
+        );
+        assert_eq!(pages[1].crop_box.map(|b| b.x1), Some(10.0));
+    }
 }
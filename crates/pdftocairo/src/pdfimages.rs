@@ -0,0 +1,129 @@
+use crate::{
+    tool::{for_each_page, invalid_output, run_tool},
+    Error, ExtractOptions, ProgressInfo,
+};
+use std::{path::Path, process::Command};
+
+/// An image that is embedded in a pdf, as listed by `pdfimages -list`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageInfo {
+    /// Starts with 1.
+    pub page_nr: u32,
+    /// The index of the image in the document.
+    pub num: u32,
+    /// `image`, `mask`, `smask` or `stencil`.
+    pub image_type: String,
+    pub width: u32,
+    pub height: u32,
+    /// E.g. `rgb`, `gray` or `icc`.
+    pub color: String,
+    pub components: u32,
+    pub bits_per_component: u32,
+    /// E.g. `jpeg`, `image` or `jbig2`.
+    pub encoding: String,
+    pub interpolate: bool,
+    pub object_nr: u32,
+    pub generation_nr: u32,
+    pub x_ppi: u32,
+    pub y_ppi: u32,
+    /// Human readable, e.g. `45.2K`.
+    pub size: String,
+}
+
+/// Invokes the `pdfimages` tool for every selected page and lists the embedded images.
+pub fn pdf_images<P: Fn(ProgressInfo)>(
+    pdf: &Path,
+    options: ExtractOptions<P>,
+) -> Result<Vec<ImageInfo>, Error> {
+    let mut result = Vec::<ImageInfo>::new();
+    for_each_page(pdf, &options, |page_nr| {
+        let stdout = run_tool(
+            "pdfimages",
+            Command::new("pdfimages")
+                .arg("-list")
+                .args(["-f", &page_nr.to_string()])
+                .args(["-l", &page_nr.to_string()])
+                .arg(pdf),
+        )?;
+        result.extend(parse_image_list(&stdout)?);
+        Ok(())
+    })?;
+    Ok(result)
+}
+
+fn parse_image_list(output: &str) -> Result<Vec<ImageInfo>, Error> {
+    // The first two lines are the column headers and a separator.
+    output
+        .lines()
+        .skip(2)
+        .filter(|line| !line.trim().is_empty())
+        .map(parse_image_line)
+        .collect()
+}
+
+/// Parses lines like
+/// `   1     0 image     512   512  rgb     3   8  jpeg   no        10  0    72    72 45.2K 5.9%`.
+fn parse_image_line(line: &str) -> Result<ImageInfo, Error> {
+    let invalid = || invalid_output("pdfimages", format!("Invalid image line '{}'", line));
+    let columns: Vec<&str> = line.split_whitespace().collect();
+    if columns.len() < 15 {
+        return Err(invalid());
+    }
+    let number = |idx: usize| columns[idx].parse::<u32>().map_err(|_| invalid());
+
+    Ok(ImageInfo {
+        page_nr: number(0)?,
+        num: number(1)?,
+        image_type: columns[2].to_owned(),
+        width: number(3)?,
+        height: number(4)?,
+        color: columns[5].to_owned(),
+        components: number(6)?,
+        bits_per_component: number(7)?,
+        encoding: columns[8].to_owned(),
+        interpolate: columns[9] == "yes",
+        object_nr: number(10)?,
+        generation_nr: number(11)?,
+        x_ppi: number(12)?,
+        y_ppi: number(13)?,
+        size: columns[14].to_owned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_image_list() {
+        let output = "page   num  type   width height color comp bpc  enc interp  object ID x-ppi y-ppi size ratio
+--------------------------------------------------------------------------------------------
+   1     0 image     512   384  rgb     3   8  jpeg   no        10  0    72    72 45.2K 7.9%
+   3     1 smask     512   384  gray    1   8  image  yes       12  0    72    72  196B 0.1%
+";
+        let images = parse_image_list(output).unwrap();
+        assert_eq!(images.len(), 2);
+        assert_eq!(
+            images[0],
+            ImageInfo {
+                page_nr: 1,
+                num: 0,
+                image_type: "image".to_owned(),
+                width: 512,
+                height: 384,
+                color: "rgb".to_owned(),
+                components: 3,
+                bits_per_component: 8,
+                encoding: "jpeg".to_owned(),
+                interpolate: false,
+                object_nr: 10,
+                generation_nr: 0,
+                x_ppi: 72,
+                y_ppi: 72,
+                size: "45.2K".to_owned(),
+            }
+        );
+        assert_eq!(images[1].image_type, "smask");
+        assert!(images[1].interpolate);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::{read_pages, Color, Error, Format, Options, Page, PdfRasterizer, ProgressInfo};
+use image::ImageFormat;
+use pdfium_render::prelude::*;
+use std::{
+    collections::HashSet,
+    fs::{create_dir_all, rename},
+    path::Path,
+};
+
+/// Poppler renders with 150 DPI by default.
+const DEFAULT_RESOLUTION: f32 = 150.0;
+
+/// Renders pdfs in-process with the pdfium library.
+///
+/// Only supports png and jpeg output. Antialiasing options and `threads` are ignored,
+/// pages are rendered sequentially.
+pub struct PdfiumRasterizer {
+    pdfium: Pdfium,
+}
+
+impl PdfiumRasterizer {
+    /// Loads the pdfium library from the directory of the executable or from the system library path.
+    pub fn new() -> Result<Self, Error> {
+        let exe_dir = std::env::current_exe()?
+            .parent()
+            .map(|p| p.to_owned())
+            .unwrap_or_default();
+        let bindings =
+            Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path(&exe_dir))
+                .or_else(|_| Pdfium::bind_to_system_library())
+                .map_err(to_error)?;
+
+        Ok(PdfiumRasterizer {
+            pdfium: Pdfium::new(bindings),
+        })
+    }
+}
+
+impl PdfRasterizer for PdfiumRasterizer {
+    fn page_count(&self, pdf: &Path) -> Result<u32, Error> {
+        let document = self
+            .pdfium
+            .load_pdf_from_file(pdf, None)
+            .map_err(to_error)?;
+        Ok(document.pages().len() as u32)
+    }
+
+    fn render_pages(
+        &self,
+        pdf: &Path,
+        target_dir: &Path,
+        options: Options<&dyn Fn(ProgressInfo)>,
+    ) -> Result<Vec<Page>, Error> {
+        let image_format = match options.format {
+            Format::Png => ImageFormat::Png,
+            Format::Jpeg => ImageFormat::Jpeg,
+            _ => {
+                return Err(Error::Unsupported(
+                    "pdfium only renders png and jpeg images",
+                ))
+            }
+        };
+        if options.color == Color::Mono {
+            return Err(Error::Unsupported(
+                "pdfium does not render monochrome images",
+            ));
+        }
+
+        if !target_dir.exists() {
+            create_dir_all(target_dir)?;
+        }
+        let has_items = target_dir.read_dir()?.next().is_some();
+        if has_items && !options.reuse_target_dir_content {
+            panic!("The given target directory must be empty!");
+        }
+
+        let document = self
+            .pdfium
+            .load_pdf_from_file(pdf, None)
+            .map_err(to_error)?;
+        let selected_pages = options.selected_pages(document.pages().len() as u32);
+        let rendered_pages: HashSet<u32> =
+            read_pages(target_dir)?.iter().map(|p| p.page_nr).collect();
+
+        let total_pages = selected_pages.len() as u32;
+        for (idx, page_nr) in selected_pages.iter().enumerate() {
+            if options
+                .cancellation
+                .as_ref()
+                .is_some_and(|c| c.is_cancelled())
+            {
+                return Err(Error::Cancelled);
+            }
+
+            if !rendered_pages.contains(page_nr) {
+                render_page(&document, target_dir, *page_nr, image_format, &options).map_err(
+                    |err| Error::PageFailed {
+                        page_nr: *page_nr,
+                        source: Box::new(err),
+                    },
+                )?;
+            }
+
+            if let Some(p) = &options.progress {
+                p(ProgressInfo {
+                    total_pages,
+                    processed_pages: idx as u32 + 1,
+                });
+            }
+        }
+
+        let selected_pages: HashSet<u32> = selected_pages.into_iter().collect();
+        let mut pages = read_pages(target_dir)?;
+        pages.retain(|p| selected_pages.contains(&p.page_nr));
+        Ok(pages)
+    }
+}
+
+fn render_page(
+    document: &PdfDocument,
+    target_dir: &Path,
+    page_nr: u32,
+    image_format: ImageFormat,
+    options: &Options<&dyn Fn(ProgressInfo)>,
+) -> Result<(), Error> {
+    let page = document
+        .pages()
+        .get((page_nr - 1) as u16)
+        .map_err(to_error)?;
+
+    let mut config = PdfRenderConfig::new();
+    if let Some(s) = options.scale_to {
+        // Scales the longer side, like `-scale-to`.
+        if page.width().value >= page.height().value {
+            config = config.set_target_width(s as i32);
+        } else {
+            config = config.set_target_height(s as i32);
+        }
+    } else if let (Some(x), Some(y)) = (options.scale_to_x, options.scale_to_y) {
+        config = config.set_target_size(x as i32, y as i32);
+    } else if let Some(x) = options.scale_to_x {
+        config = config.set_target_width(x as i32);
+    } else if let Some(y) = options.scale_to_y {
+        config = config.set_target_height(y as i32);
+    } else {
+        let resolution = options.resolution.map_or(DEFAULT_RESOLUTION, |r| r as f32);
+        config = config.scale_page_by_factor(resolution / 72.0);
+    }
+    if options.transparent && image_format == ImageFormat::Png {
+        config = config.set_clear_color(PdfColor::new(255, 255, 255, 0));
+    }
+    config = config.use_grayscale_rendering(options.color == Color::Gray);
+
+    let mut image = page
+        .render_with_config(&config)
+        .map_err(to_error)?
+        .as_image();
+    if let Some(crop) = &options.crop {
+        image = image.crop_imm(crop.x, crop.y, crop.width, crop.height);
+    }
+    if options.color == Color::Gray {
+        image = image.grayscale();
+    } else if !options.transparent || image_format == ImageFormat::Jpeg {
+        image = image.to_rgb8().into();
+    }
+
+    // Write to a temporary file first, so that only complete pages are listed by `read_pages`.
+    let ext = options.format.ext();
+    let tmp_file = target_dir.join(format!("tmp-{}.{}", page_nr, ext));
+    image
+        .save_with_format(&tmp_file, image_format)
+        .map_err(|err| Error::Rasterizer(err.to_string()))?;
+    rename(&tmp_file, target_dir.join(format!("p-{}.{}", page_nr, ext)))?;
+    Ok(())
+}
+
+fn to_error(err: PdfiumError) -> Error {
+    match err {
+        PdfiumError::IoError(err) => Error::Io(err),
+        err => Error::Rasterizer(err.to_string()),
+    }
+}
@@ -1,14 +1,18 @@
 use std::{
-    fs::create_dir_all,
-    io,
+    collections::HashSet,
+    fs::{create_dir_all, remove_file, rename},
+    io::Read,
     path::{Path, PathBuf},
-    process::Command,
-    sync::{Arc, Mutex},
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
     thread,
     time::Duration,
 };
 
-use crate::pdf_info;
+use crate::{pdf_info, Error, PageSelection};
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Page {
@@ -42,6 +46,21 @@ impl Format {
             Format::Eps => vec!["-eps".to_owned()],
         }
     }
+
+    pub(crate) fn ext(&self) -> &'static str {
+        match self {
+            Format::Png => "png",
+            Format::Jpeg => "jpg",
+            Format::Pdf => "pdf",
+            Format::Svg => "svg",
+            Format::Eps => "eps",
+        }
+    }
+
+    /// Vector formats are written to the given file name, image formats get an extension appended.
+    fn is_image(&self) -> bool {
+        matches!(self, Format::Png | Format::Jpeg)
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -69,15 +88,85 @@ pub enum Pages {
 }
 
 impl Pages {
-    fn to_args(&self) -> Vec<String> {
+    fn contains(&self, page_nr: u32) -> bool {
+        let is_odd = page_nr % 2 == 1;
         match self {
-            Pages::All => vec![],
-            Pages::Odd => vec!["-o".to_owned()],
-            Pages::Even => vec!["-e".to_owned()],
+            Pages::All => true,
+            Pages::Odd => is_odd,
+            Pages::Even => !is_odd,
         }
     }
 }
 
+/// Can be used to abort a running `pdftocairo` call from another thread.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Kills all running `pdftocairo` processes of the calls that use this token.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Antialias {
+    Default,
+    None,
+    Gray,
+    Subpixel,
+    Fast,
+    Good,
+    Best,
+}
+
+impl Antialias {
+    fn to_args(&self) -> Vec<String> {
+        let value = match self {
+            Antialias::Default => "default",
+            Antialias::None => "none",
+            Antialias::Gray => "gray",
+            Antialias::Subpixel => "subpixel",
+            Antialias::Fast => "fast",
+            Antialias::Good => "good",
+            Antialias::Best => "best",
+        };
+        vec!["-antialias".to_owned(), value.to_owned()]
+    }
+}
+
+/// An area of the rendered page in pixels.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CropArea {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl CropArea {
+    fn to_args(&self) -> Vec<String> {
+        vec![
+            "-x".to_owned(),
+            self.x.to_string(),
+            "-y".to_owned(),
+            self.y.to_string(),
+            "-W".to_owned(),
+            self.width.to_string(),
+            "-H".to_owned(),
+            self.height.to_string(),
+        ]
+    }
+}
+
 pub struct Options<P> {
     pub format: Format,
     pub color: Color,
@@ -86,40 +175,71 @@ pub struct Options<P> {
     pub first_page: Option<u32>,
     /// One based
     pub last_page: Option<u32>,
+    /// Further restricts the pages between `first_page` and `last_page`.
+    pub selection: Option<PageSelection>,
+    /// The resolution in DPI. Poppler uses 150 DPI by default.
+    pub resolution: Option<f64>,
+    /// Scales each page so that its longer side has this size in pixels.
+    pub scale_to: Option<u32>,
+    /// Scales each page to this width in pixels.
+    /// Keeps the aspect ratio if `scale_to_y` is not set.
+    pub scale_to_x: Option<u32>,
+    /// Scales each page to this height in pixels.
+    /// Keeps the aspect ratio if `scale_to_x` is not set.
+    pub scale_to_y: Option<u32>,
+    /// Only renders this area of each page.
+    pub crop: Option<CropArea>,
+    /// Uses a transparent instead of a white background. Only supported for png.
+    pub transparent: bool,
+    pub antialias: Option<Antialias>,
+    /// Called after every rendered page.
     pub progress: Option<P>,
+    /// Pages that already exist in the target directory are not rendered again.
     pub reuse_target_dir_content: bool,
+    /// The number of `pdftocairo` processes that render pages in parallel.
+    /// Defaults to the number of cores.
+    pub threads: Option<usize>,
+    pub cancellation: Option<CancellationToken>,
 }
 
 impl<P> Options<P> {
-    fn exported_page_count(&self, pdf_page_count: u32) -> u32 {
-        let mut count = 0;
+    pub(crate) fn selected_pages(&self, pdf_page_count: u32) -> Vec<u32> {
         let first_page = self.first_page.unwrap_or(1);
-        let last_page = self.last_page.unwrap_or(pdf_page_count);
-
-        for i in 1..(pdf_page_count + 1) {
-            match self.pages {
-                Pages::All => {}
-                Pages::Odd => {
-                    // this might be wrong
-                    if i % 2 == 1 {
-                        continue;
-                    }
-                }
-                Pages::Even => {
-                    if i % 2 == 0 {
-                        continue;
-                    }
-                }
-            }
-            if i < first_page {
-                continue;
-            }
-            if i > last_page {
-                continue;
-            }
-            count += 1;
+        let last_page = self.last_page.unwrap_or(pdf_page_count).min(pdf_page_count);
+
+        (first_page..=last_page)
+            .filter(|page_nr| self.pages.contains(*page_nr))
+            .filter(|page_nr| self.selection.as_ref().is_none_or(|s| s.contains(*page_nr)))
+            .collect()
+    }
+
+    /// The arguments that are shared by all pages.
+    fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::<String>::new();
+        args.extend(self.color.to_args());
+        args.extend(self.format.to_args());
+        if let Some(r) = self.resolution {
+            args.extend(vec!["-r".to_owned(), r.to_string()]);
+        }
+        if let Some(s) = self.scale_to {
+            args.extend(vec!["-scale-to".to_owned(), s.to_string()]);
+        }
+        if self.scale_to_x.is_some() || self.scale_to_y.is_some() {
+            // -1 keeps the aspect ratio
+            let to_arg = |s: Option<u32>| s.map_or("-1".to_owned(), |s| s.to_string());
+            args.extend(vec!["-scale-to-x".to_owned(), to_arg(self.scale_to_x)]);
+            args.extend(vec!["-scale-to-y".to_owned(), to_arg(self.scale_to_y)]);
         }
-        count
+        if let Some(crop) = &self.crop {
+            args.extend(crop.to_args());
+        }
+        if self.transparent {
+            args.push("-transp".to_owned());
+        }
+        if let Some(antialias) = &self.antialias {
+            args.extend(antialias.to_args());
+        }
+        args
     }
 }
 
@@ -130,88 +250,197 @@ impl<P> Default for Options<P> {
             color: Color::Color,
             first_page: None,
             last_page: None,
+            selection: None,
+            resolution: None,
+            scale_to: None,
+            scale_to_x: None,
+            scale_to_y: None,
+            crop: None,
+            transparent: false,
+            antialias: None,
             pages: Pages::All,
             progress: None,
             reuse_target_dir_content: false,
+            threads: None,
+            cancellation: None,
         }
     }
 }
 
+/// Renders the selected pages of the given pdf to `target_dir`.
+/// Pages are rendered one by one by several `pdftocairo` processes in parallel.
 pub fn pdftocairo<P: Fn(ProgressInfo)>(
     pdf: &Path,
     target_dir: &Path,
     options: Options<P>,
-) -> Result<Vec<Page>, io::Error> {
+) -> Result<Vec<Page>, Error> {
     if !target_dir.exists() {
         create_dir_all(target_dir)?;
     }
 
     let pdf_info = pdf_info(pdf)?;
+    let selected_pages = options.selected_pages(pdf_info.page_count());
 
-    let page_count = options.exported_page_count(pdf_info.page_count());
-
-    let mut last = ProgressInfo {
-        total_pages: 0,
-        processed_pages: 0,
+    let has_items = target_dir.read_dir()?.next().is_some();
+    if has_items && !options.reuse_target_dir_content {
+        panic!("The given target directory must be empty!");
+    }
+    let rendered_pages: HashSet<u32> = read_pages(target_dir)?.iter().map(|p| p.page_nr).collect();
+    let pending_pages: Vec<u32> = selected_pages
+        .iter()
+        .copied()
+        .filter(|page_nr| !rendered_pages.contains(page_nr))
+        .collect();
+
+    let total_pages = selected_pages.len() as u32;
+    let mut processed_pages = total_pages - pending_pages.len() as u32;
+    let report_progress = |processed_pages: u32| {
+        if let Some(p) = &options.progress {
+            p(ProgressInfo {
+                total_pages,
+                processed_pages,
+            });
+        }
     };
-    let mut report_progress = |p: u32| {
-        let new = ProgressInfo {
-            total_pages: page_count,
-            processed_pages: p,
-        };
-        if new != last {
-            if let Some(p) = &options.progress {
-                p(new.clone());
+    report_progress(processed_pages);
+
+    let args = options.to_args();
+    let format = &options.format;
+    let cancellation = options.cancellation.clone().unwrap_or_default();
+    // Stops the other workers if a page fails.
+    let failed = CancellationToken::new();
+    let threads = options
+        .threads
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+        .max(1);
+    let queue = Mutex::new(pending_pages.into_iter());
+    let mut first_error: Option<Error> = None;
+
+    thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel::<Result<u32, Error>>();
+        for _ in 0..threads {
+            let sender = sender.clone();
+            let (queue, args, cancellation, failed) = (&queue, &args, &cancellation, &failed);
+            scope.spawn(move || loop {
+                let is_aborted = || cancellation.is_cancelled() || failed.is_cancelled();
+                let page_nr = match queue.lock().unwrap().next() {
+                    Some(page_nr) if !is_aborted() => page_nr,
+                    _ => break,
+                };
+                let result = render_page(pdf, target_dir, page_nr, format, args, &is_aborted)
+                    .map(|_| page_nr)
+                    .map_err(|err| match err {
+                        Error::Cancelled => Error::Cancelled,
+                        err => Error::PageFailed {
+                            page_nr,
+                            source: Box::new(err),
+                        },
+                    });
+                let is_err = result.is_err();
+                sender.send(result).unwrap();
+                if is_err {
+                    break;
+                }
+            });
+        }
+        // The loop below ends once all workers dropped their sender.
+        drop(sender);
+
+        for result in receiver {
+            match result {
+                Ok(_) => {
+                    processed_pages += 1;
+                    report_progress(processed_pages);
+                }
+                Err(Error::Cancelled) => {}
+                Err(err) => {
+                    failed.cancel();
+                    first_error.get_or_insert(err);
+                }
             }
-            last = new;
         }
-    };
+    });
 
-    let has_items = target_dir.read_dir()?.next().is_some();
-    if has_items {
-        if !options.reuse_target_dir_content {
-            panic!("The given target directory must be empty!");
-        }
+    if cancellation.is_cancelled() {
+        return Err(Error::Cancelled);
+    }
+    if let Some(err) = first_error {
+        return Err(err);
+    }
+
+    let selected_pages: HashSet<u32> = selected_pages.into_iter().collect();
+    let mut pages = read_pages(target_dir)?;
+    pages.retain(|p| selected_pages.contains(&p.page_nr));
+    Ok(pages)
+}
+
+/// Renders a single page to `p-<page_nr>.<ext>`.
+/// The page is rendered to a temporary file first, so that only complete pages are listed by `read_pages`.
+fn render_page(
+    pdf: &Path,
+    target_dir: &Path,
+    page_nr: u32,
+    format: &Format,
+    args: &[String],
+    is_aborted: &dyn Fn() -> bool,
+) -> Result<(), Error> {
+    let tmp_path = target_dir.join(format!("tmp-{}", page_nr));
+    let tmp_file = tmp_path.with_extension(format.ext());
+
+    let mut cmd = Command::new("pdftocairo");
+    cmd.args(args);
+    cmd.args(["-f", &page_nr.to_string(), "-l", &page_nr.to_string()]);
+    cmd.arg(pdf);
+    if format.is_image() {
+        // Writes `tmp-<page_nr>.<ext>` instead of appending the page number.
+        cmd.arg("-singlefile");
+        cmd.arg(&tmp_path);
     } else {
-        let result = Arc::new(Mutex::<Option<()>>::new(None));
-
-        let pdf2 = pdf.to_owned();
-        let target_dir2 = target_dir.to_owned();
-        let result2 = result.clone();
-
-        let mut cmd = Command::new(&"pdftocairo");
-        cmd.arg(&pdf2);
-        cmd.arg(&target_dir2.join("p"));
-        cmd.args(&options.color.to_args());
-        cmd.args(&options.format.to_args());
-        cmd.args(&options.pages.to_args());
-        if let Some(f) = options.first_page {
-            cmd.args(vec!["-f", &f.to_string()]);
+        cmd.arg(&tmp_file);
+    }
+
+    let mut child = cmd.stdout(Stdio::null()).stderr(Stdio::piped()).spawn()?;
+    // Drains stderr while polling, otherwise pdftocairo blocks once the pipe buffer is full of warnings.
+    let mut pipe = child.stderr.take().unwrap();
+    let stderr_reader = thread::spawn(move || {
+        let mut stderr = String::new();
+        let _ = pipe.read_to_string(&mut stderr);
+        stderr
+    });
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
         }
-        if let Some(l) = options.last_page {
-            cmd.args(vec!["-l", &l.to_string()]);
+        if is_aborted() {
+            // Fails if the process exited in the meantime.
+            let _ = child.kill();
+            child.wait()?;
+            let _ = stderr_reader.join();
+            let _ = remove_file(&tmp_file);
+            return Err(Error::Cancelled);
         }
+        thread::sleep(Duration::from_millis(20));
+    };
 
-        thread::spawn(move || {
-            let r = cmd
-                .status()
-                .expect(&format!("Cairo should extract pdf slides"));
-            if !r.success() {
-                // TODO
-            }
-            let mut m = result2.lock().unwrap();
-            *m = Some(());
+    let stderr = stderr_reader.join().unwrap_or_default();
+    if !status.success() {
+        return Err(Error::ToolFailed {
+            tool: "pdftocairo",
+            status,
+            stderr,
         });
-
-        while result.lock().unwrap().is_none() {
-            report_progress(target_dir.read_dir()?.count() as u32);
-
-            thread::sleep(Duration::from_millis(500));
-        }
     }
 
-    report_progress(target_dir.read_dir()?.count() as u32);
+    rename(
+        &tmp_file,
+        target_dir.join(format!("p-{}.{}", page_nr, format.ext())),
+    )?;
+    Ok(())
+}
 
+/// Lists the pages that have been extracted to the given directory.
+pub fn read_pages(target_dir: &Path) -> Result<Vec<Page>, Error> {
     let mut result = Vec::<Page>::new();
 
     for item in target_dir.read_dir()? {
@@ -219,9 +448,15 @@ pub fn pdftocairo<P: Fn(ProgressInfo)>(
         let file_name = item.file_name(); // e.g. p-01.png
         let file_name = file_name.to_string_lossy();
 
+        // Skips pages that are still being rendered.
         let name_without_ext = file_name.split('.').next().unwrap();
-        let page_nr_str = &name_without_ext[2..];
-        let page_nr: u32 = page_nr_str.parse().unwrap();
+        let page_nr: u32 = match name_without_ext
+            .strip_prefix("p-")
+            .and_then(|n| n.parse().ok())
+        {
+            Some(page_nr) => page_nr,
+            None => continue,
+        };
         result.push(Page {
             image_path: item.path(),
             page_nr,
@@ -262,8 +497,33 @@ mod tests {
         .unwrap();
 
         assert_eq!(
-            pages.iter().map(|p| p.index).collect::<Vec<_>>(),
-            vec![1, 3, 5, 7]
+            pages.iter().map(|p| p.page_nr).collect::<Vec<_>>(),
+            vec![2, 4, 6, 8]
+        );
+    }
+
+    #[test]
+    fn test_selected_pages() {
+        let options = |pages: Pages, selection: Option<&str>| Options::<fn(ProgressInfo)> {
+            first_page: Some(2),
+            last_page: Some(9),
+            pages,
+            selection: selection.map(|s| s.parse().unwrap()),
+            ..Options::default()
+        };
+
+        assert_eq!(
+            options(Pages::All, None).selected_pages(8),
+            vec![2, 3, 4, 5, 6, 7, 8]
+        );
+        assert_eq!(options(Pages::Odd, None).selected_pages(8), vec![3, 5, 7]);
+        assert_eq!(
+            options(Pages::Even, None).selected_pages(8),
+            vec![2, 4, 6, 8]
+        );
+        assert_eq!(
+            options(Pages::Even, Some("1-4,7-")).selected_pages(8),
+            vec![2, 4, 8]
         );
     }
 }
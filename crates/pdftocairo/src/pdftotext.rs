@@ -0,0 +1,253 @@
+use crate::{
+    tool::{invalid_output, page_range, run_tool_with_progress},
+    Error, ProgressInfo,
+};
+use std::{path::Path, process::Command};
+
+/// Selects the pages of the text and image extraction functions.
+pub struct ExtractOptions<P> {
+    /// One based
+    pub first_page: Option<u32>,
+    /// One based
+    pub last_page: Option<u32>,
+    /// Called after every page.
+    pub progress: Option<P>,
+}
+
+impl<P> Default for ExtractOptions<P> {
+    fn default() -> Self {
+        ExtractOptions {
+            first_page: None,
+            last_page: None,
+            progress: None,
+        }
+    }
+}
+
+/// A word and its bounding box in PDF points, relative to the top left corner of the page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Word {
+    pub text: String,
+    pub x_min: f64,
+    pub y_min: f64,
+    pub x_max: f64,
+    pub y_max: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageWords {
+    /// Starts with 1.
+    pub page_nr: u32,
+    pub width: f64,
+    pub height: f64,
+    pub words: Vec<Word>,
+}
+
+/// Invokes the `pdftotext` tool once and returns the text of every selected page.
+/// The first entry is the text of the first selected page.
+pub fn pdf_to_text<P: Fn(ProgressInfo)>(
+    pdf: &Path,
+    options: ExtractOptions<P>,
+) -> Result<Vec<String>, Error> {
+    let (first_page, last_page) = page_range(pdf, &options)?;
+    let stdout = run_tool_with_progress(
+        "pdftotext",
+        Command::new("pdftotext")
+            .args(["-enc", "UTF-8"])
+            .args(["-f", &first_page.to_string()])
+            .args(["-l", &last_page.to_string()])
+            .arg(pdf)
+            .arg("-"),
+        (last_page + 1).saturating_sub(first_page),
+        options.progress.as_ref(),
+        // Every page is terminated with a form feed.
+        |line| line.iter().filter(|&&b| b == b'\x0c').count(),
+    )?;
+
+    Ok(split_pages(&stdout))
+}
+
+/// Invokes the `pdftotext` tool once and returns the words of every selected page with their bounding boxes.
+pub fn pdf_words<P: Fn(ProgressInfo)>(
+    pdf: &Path,
+    options: ExtractOptions<P>,
+) -> Result<Vec<PageWords>, Error> {
+    let (first_page, last_page) = page_range(pdf, &options)?;
+    let stdout = run_tool_with_progress(
+        "pdftotext",
+        Command::new("pdftotext")
+            .args(["-bbox-layout", "-enc", "UTF-8"])
+            .args(["-f", &first_page.to_string()])
+            .args(["-l", &last_page.to_string()])
+            .arg(pdf)
+            .arg("-"),
+        (last_page + 1).saturating_sub(first_page),
+        options.progress.as_ref(),
+        |line| (line.trim_ascii() == b"</page>") as usize,
+    )?;
+
+    let mut pages = parse_bbox_html(&stdout)?;
+    for page in &mut pages {
+        page.page_nr += first_page - 1;
+    }
+    Ok(pages)
+}
+
+/// `pdftotext` terminates every page with a form feed.
+fn split_pages(text: &str) -> Vec<String> {
+    let mut pages: Vec<String> = text.split('\x0c').map(|p| p.to_owned()).collect();
+    // Drop the empty remainder after the last form feed.
+    if pages.last().is_some_and(|p| p.is_empty()) {
+        pages.pop();
+    }
+    pages
+}
+
+/// Parses the xhtml output of `pdftotext -bbox`, which puts every element on its own line:
+/// `<page width="720.000000" height="540.000000">` and
+/// `<word xMin="56.69" yMin="35.10" xMax="93.10" yMax="49.44">Hello</word>`.
+/// Page numbers are counted from 1.
+fn parse_bbox_html(html: &str) -> Result<Vec<PageWords>, Error> {
+    let mut pages = Vec::<PageWords>::new();
+
+    for line in html.lines() {
+        let line = line.trim();
+        if line.starts_with("<page ") {
+            pages.push(PageWords {
+                page_nr: pages.len() as u32 + 1,
+                width: parse_attribute(line, "width")?,
+                height: parse_attribute(line, "height")?,
+                words: Vec::new(),
+            });
+        } else if line.starts_with("<word ") {
+            let text_start = line
+                .find('>')
+                .ok_or_else(|| invalid_output("pdftotext", format!("Invalid word '{}'", line)))?;
+            let text = line[text_start + 1..].trim_end_matches("</word>");
+            let word = Word {
+                text: decode_entities(text),
+                x_min: parse_attribute(line, "xMin")?,
+                y_min: parse_attribute(line, "yMin")?,
+                x_max: parse_attribute(line, "xMax")?,
+                y_max: parse_attribute(line, "yMax")?,
+            };
+            pages
+                .last_mut()
+                .ok_or_else(|| invalid_output("pdftotext", "Word outside of a page"))?
+                .words
+                .push(word);
+        }
+    }
+
+    Ok(pages)
+}
+
+fn parse_attribute(element: &str, name: &str) -> Result<f64, Error> {
+    let prefix = format!(" {}=\"", name);
+    element
+        .find(&prefix)
+        .map(|start| &element[start + prefix.len()..])
+        .and_then(|value| value.split('"').next())
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| {
+            invalid_output(
+                "pdftotext",
+                format!("Missing attribute '{}' in '{}'", name, element),
+            )
+        })
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find(';') {
+            Some(end) => end,
+            None => break,
+        };
+        let decoded = match &rest[1..end] {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            entity => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16).ok())
+                .unwrap_or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(std::char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_pages() {
+        assert_eq!(
+            split_pages("Page 1\n\x0c\x0cPage 3\n\x0c"),
+            vec!["Page 1\n".to_owned(), "".to_owned(), "Page 3\n".to_owned()]
+        );
+    }
+
+    #[test]
+    fn test_parse_bbox_html() {
+        let html = r#"<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Transitional//EN" "http://www.w3.org/TR/xhtml1/DTD/xhtml1-transitional.dtd">
+<html xmlns="http://www.w3.org/1999/xhtml">
+<head>
+<title></title>
+</head>
+<body>
+<doc>
+  <page width="720.000000" height="540.000000">
+    <word xMin="56.500000" yMin="35.000000" xMax="93.000000" yMax="49.500000">Q&amp;A</word>
+    <word xMin="95.000000" yMin="35.000000" xMax="120.000000" yMax="49.500000">&lt;x&#62;</word>
+  </page>
+  <page width="720.000000" height="540.000000">
+  </page>
+</doc>
+</body>
+</html>
+"#;
+        let pages = parse_bbox_html(html).unwrap();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].page_nr, 1);
+        assert_eq!(pages[0].width, 720.0);
+        assert_eq!(
+            pages[0].words,
+            vec![
+                Word {
+                    text: "Q&A".to_owned(),
+                    x_min: 56.5,
+                    y_min: 35.0,
+                    x_max: 93.0,
+                    y_max: 49.5,
+                },
+                Word {
+                    text: "<x>".to_owned(),
+                    x_min: 95.0,
+                    y_min: 35.0,
+                    x_max: 120.0,
+                    y_max: 49.5,
+                }
+            ]
+        );
+        assert!(pages[1].words.is_empty());
+    }
+}
@@ -0,0 +1,46 @@
+use crate::{pdf_info, pdftocairo, Error, Options, Page, ProgressInfo};
+use std::path::Path;
+
+/// Renders pdf pages to images.
+pub trait PdfRasterizer: Send + Sync {
+    fn page_count(&self, pdf: &Path) -> Result<u32, Error>;
+
+    /// Renders the selected pages to `target_dir`, like `pdftocairo` does.
+    fn render_pages(
+        &self,
+        pdf: &Path,
+        target_dir: &Path,
+        options: Options<&dyn Fn(ProgressInfo)>,
+    ) -> Result<Vec<Page>, Error>;
+}
+
+/// Uses the poppler executables `pdfinfo` and `pdftocairo`, which must be in the `PATH`.
+#[derive(Debug, Default, Clone)]
+pub struct PopplerRasterizer;
+
+impl PdfRasterizer for PopplerRasterizer {
+    fn page_count(&self, pdf: &Path) -> Result<u32, Error> {
+        Ok(pdf_info(pdf)?.page_count())
+    }
+
+    fn render_pages(
+        &self,
+        pdf: &Path,
+        target_dir: &Path,
+        options: Options<&dyn Fn(ProgressInfo)>,
+    ) -> Result<Vec<Page>, Error> {
+        pdftocairo(pdf, target_dir, options)
+    }
+}
+
+/// Returns the in-process pdfium renderer if the `pdfium` feature is enabled
+/// and the pdfium library can be loaded, otherwise the poppler renderer.
+pub fn default_rasterizer() -> Box<dyn PdfRasterizer> {
+    #[cfg(feature = "pdfium")]
+    {
+        if let Ok(rasterizer) = crate::PdfiumRasterizer::new() {
+            return Box::new(rasterizer);
+        }
+    }
+    Box::new(PopplerRasterizer)
+}
@@ -0,0 +1,113 @@
+use crate::{pdf_info, Error, ExtractOptions, ProgressInfo};
+use std::{
+    io::{BufRead, BufReader, Read},
+    path::Path,
+    process::{Command, Stdio},
+    thread,
+};
+
+/// Runs the given poppler tool and returns its stdout.
+pub(crate) fn run_tool(tool: &'static str, cmd: &mut Command) -> Result<String, Error> {
+    let output = cmd.output()?;
+
+    if !output.status.success() {
+        return Err(Error::ToolFailed {
+            tool,
+            status: output.status,
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Runs the given poppler tool, which writes `total_pages` pages one after another to stdout, and returns its stdout.
+/// `page_ends` counts the pages that end in a line of the output.
+/// Reports the progress whenever a page ends.
+pub(crate) fn run_tool_with_progress<P: Fn(ProgressInfo)>(
+    tool: &'static str,
+    cmd: &mut Command,
+    total_pages: u32,
+    progress: Option<&P>,
+    page_ends: impl Fn(&[u8]) -> usize,
+) -> Result<String, Error> {
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    // Drains stderr while reading stdout, otherwise the tool blocks once the pipe buffer is full of warnings.
+    let mut pipe = child.stderr.take().unwrap();
+    let stderr_reader = thread::spawn(move || {
+        let mut stderr = String::new();
+        let _ = pipe.read_to_string(&mut stderr);
+        stderr
+    });
+
+    let mut stdout = Vec::<u8>::new();
+    let mut processed_pages = 0;
+    let mut reader = BufReader::new(child.stdout.take().unwrap());
+    loop {
+        let line_start = stdout.len();
+        if reader.read_until(b'\n', &mut stdout)? == 0 {
+            break;
+        }
+        let ended_pages = page_ends(&stdout[line_start..]) as u32;
+        if ended_pages > 0 {
+            processed_pages = (processed_pages + ended_pages).min(total_pages);
+            if let Some(progress) = progress {
+                progress(ProgressInfo {
+                    total_pages,
+                    processed_pages,
+                });
+            }
+        }
+    }
+
+    let status = child.wait()?;
+    let stderr = stderr_reader.join().unwrap_or_default();
+    if !status.success() {
+        return Err(Error::ToolFailed {
+            tool,
+            status,
+            stderr,
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&stdout).into_owned())
+}
+
+pub(crate) fn invalid_output(tool: &'static str, message: impl Into<String>) -> Error {
+    Error::InvalidOutput {
+        tool,
+        message: message.into(),
+    }
+}
+
+/// Calls `f` with every page number in the range of the given options
+/// and reports the progress after each page.
+pub(crate) fn for_each_page<P: Fn(ProgressInfo)>(
+    pdf: &Path,
+    options: &ExtractOptions<P>,
+    mut f: impl FnMut(u32) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let (first_page, last_page) = page_range(pdf, options)?;
+    let total_pages = (last_page + 1).saturating_sub(first_page);
+
+    for (idx, page_nr) in (first_page..=last_page).enumerate() {
+        f(page_nr)?;
+        if let Some(progress) = &options.progress {
+            progress(ProgressInfo {
+                total_pages,
+                processed_pages: idx as u32 + 1,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Returns the first and the last selected page, both one based.
+pub(crate) fn page_range<P>(pdf: &Path, options: &ExtractOptions<P>) -> Result<(u32, u32), Error> {
+    let first_page = options.first_page.unwrap_or(1);
+    let last_page = match options.last_page {
+        Some(last_page) => last_page,
+        None => pdf_info(pdf)?.page_count(),
+    };
+    Ok((first_page, last_page))
+}